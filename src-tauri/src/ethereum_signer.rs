@@ -3,6 +3,7 @@ use alloy_rlp::{Decodable, RlpDecodable};
 use alloy_sol_types::{Eip712Domain, SolStruct, sol};
 use anyhow::{Result, ensure};
 use bip32::XPrv;
+use secp256k1::ecdsa::{RecoverableSignature, RecoveryId};
 use secp256k1::{Message as Secp256k1Message, Secp256k1, SecretKey};
 use std::str::FromStr;
 
@@ -71,7 +72,7 @@ pub struct EthereumTransactionRequest {
     pub unsigned_transaction: String,
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EthereumTransactionSignature {
     pub y_parity: u8,
@@ -89,7 +90,7 @@ pub struct EthereumPermitRequest {
     pub deadline: String,
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct EthereumPermitSignature {
     pub v: u8,
@@ -97,6 +98,17 @@ pub struct EthereumPermitSignature {
     pub s: String,
 }
 
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EthereumPermitVerificationRequest {
+    pub chain_id: u64,
+    pub owner: String,
+    pub spender: String,
+    #[serde(flatten)]
+    pub permit: EthereumPermitRequest,
+    pub signature: EthereumPermitSignature,
+}
+
 pub fn derive_address_at_path(mnemonic: &str, hd_path: &str) -> Result<String> {
     derive_ethereum_address_from_hd_key(derive_hd_key_with_path(mnemonic, hd_path)?)
 }
@@ -152,7 +164,31 @@ pub fn derive_address_from_private_key(private_key: &str) -> Result<String> {
     ))
 }
 
+/// Returns `(address, compressed public key)` for each path, deriving the BIP-39 seed only once.
+pub fn derive_public_identities(
+    mnemonic: &str,
+    hd_paths: &[String],
+) -> Result<Vec<(String, String)>> {
+    let seed = bip39::Mnemonic::from_str(mnemonic)?.to_seed("");
+    hd_paths
+        .iter()
+        .map(|hd_path| {
+            let hd_key = derive_hd_key_from_seed(&seed, hd_path)?;
+            let public_key = hd_key.private_key().verifying_key().to_encoded_point(true);
+            Ok((
+                derive_ethereum_address_from_hd_key(hd_key)?,
+                format!("0x{}", hex::encode(public_key.as_bytes())),
+            ))
+        })
+        .collect()
+}
+
 fn derive_hd_key_with_path(mnemonic: &str, path: &str) -> Result<XPrv> {
+    let seed = bip39::Mnemonic::from_str(mnemonic)?.to_seed("");
+    derive_hd_key_from_seed(&seed, path)
+}
+
+fn derive_hd_key_from_seed(seed: &[u8; 64], path: &str) -> Result<XPrv> {
     ensure!(
         path.starts_with(ETHEREUM_HD_PATH_PREFIX),
         "Ethereum derivations must use an m/44'/60'/... path"
@@ -164,7 +200,6 @@ fn derive_hd_key_with_path(mnemonic: &str, path: &str) -> Result<XPrv> {
         "Ethereum custom derivations must use hardened segments after m/44'/60'/"
     );
 
-    let seed = bip39::Mnemonic::from_str(mnemonic)?.to_seed("");
     let path = bip32::DerivationPath::from_str(path)?;
     Ok(bip32::XPrv::derive_from_path(seed, &path)?)
 }
@@ -213,43 +248,32 @@ fn sign_personal_message_with_secret_key(secret_key: SecretKey, message: &str) -
     Ok(format!("0x{}", hex::encode(signature_bytes)))
 }
 
+pub fn recover_personal_message_signer(message: &str, signature: &str) -> Result<String> {
+    let signature_bytes = decode_hex(signature)?;
+    ensure!(
+        signature_bytes.len() == 65,
+        "Ethereum personal signature must be 65 bytes"
+    );
+    let message_bytes = decode_ethereum_message(message)?;
+    let digest = ethereum_personal_message_digest(&message_bytes);
+    let signer = recover_address_from_compact(digest, signature_bytes[64], &signature_bytes[..64])?;
+    Ok(to_checksummed_ethereum_address(&signer))
+}
+
 pub fn sign_permit(
     mnemonic: &str,
     hd_path: &str,
     policy: &EthereumSignerPolicy,
     request: &EthereumPermitRequest,
 ) -> Result<EthereumPermitSignature> {
-    let token_address = parse_ethereum_address(&request.token_address)?;
-    ensure!(
-        policy
-            .token_addresses
-            .iter()
-            .any(|allowed| allowed == &token_address),
-        "Ethereum permit token is not allowed"
-    );
-
-    let value = parse_u256(&request.value)?;
-    let nonce = parse_u256(&request.nonce)?;
-    let deadline = parse_u256(&request.deadline)?;
-    let domain = Eip712Domain::new(
-        Some(request.token_name.clone().into()),
-        Some("1".into()),
-        Some(U256::from(policy.chain_id)),
-        Some(Address::from(token_address)),
-        None,
-    );
-    let permit = Permit {
-        owner: Address::from(derive_ethereum_address_bytes(mnemonic, hd_path)?),
-        spender: Address::from(policy.gateway_address),
-        value,
-        nonce,
-        deadline,
-    };
-    let signature = sign_digest(
-        mnemonic,
-        hd_path,
-        permit.eip712_signing_hash(&domain).into(),
+    ensure_permit_token_allowed(policy, request)?;
+    let digest = permit_signing_hash(
+        policy.chain_id,
+        derive_ethereum_address_bytes(mnemonic, hd_path)?,
+        policy.gateway_address,
+        request,
     )?;
+    let signature = sign_digest(mnemonic, hd_path, digest)?;
 
     Ok(EthereumPermitSignature {
         v: signature.0,
@@ -264,6 +288,43 @@ pub fn sign_permit_with_private_key(
     request: &EthereumPermitRequest,
 ) -> Result<EthereumPermitSignature> {
     let secret_key = parse_private_key(private_key)?;
+    ensure_permit_token_allowed(policy, request)?;
+    let digest = permit_signing_hash(
+        policy.chain_id,
+        derive_ethereum_address_bytes_from_secret_key(&secret_key),
+        policy.gateway_address,
+        request,
+    )?;
+    let signature = sign_digest_with_secret_key(secret_key, digest)?;
+
+    Ok(EthereumPermitSignature {
+        v: signature.0,
+        r: signature.1,
+        s: signature.2,
+    })
+}
+
+pub fn verify_permit(request: &EthereumPermitVerificationRequest) -> Result<bool> {
+    let owner = parse_ethereum_address(&request.owner)?;
+    let digest = permit_signing_hash(
+        request.chain_id,
+        owner,
+        parse_ethereum_address(&request.spender)?,
+        &request.permit,
+    )?;
+    let signer = recover_address_from_digest(
+        digest,
+        request.signature.v,
+        &request.signature.r,
+        &request.signature.s,
+    )?;
+    Ok(signer == owner)
+}
+
+fn ensure_permit_token_allowed(
+    policy: &EthereumSignerPolicy,
+    request: &EthereumPermitRequest,
+) -> Result<()> {
     let token_address = parse_ethereum_address(&request.token_address)?;
     ensure!(
         policy
@@ -272,32 +333,31 @@ pub fn sign_permit_with_private_key(
             .any(|allowed| allowed == &token_address),
         "Ethereum permit token is not allowed"
     );
+    Ok(())
+}
 
-    let value = parse_u256(&request.value)?;
-    let nonce = parse_u256(&request.nonce)?;
-    let deadline = parse_u256(&request.deadline)?;
+fn permit_signing_hash(
+    chain_id: u64,
+    owner: [u8; 20],
+    spender: [u8; 20],
+    request: &EthereumPermitRequest,
+) -> Result<[u8; 32]> {
+    let token_address = parse_ethereum_address(&request.token_address)?;
     let domain = Eip712Domain::new(
         Some(request.token_name.clone().into()),
         Some("1".into()),
-        Some(U256::from(policy.chain_id)),
+        Some(U256::from(chain_id)),
         Some(Address::from(token_address)),
         None,
     );
     let permit = Permit {
-        owner: Address::from(derive_ethereum_address_bytes_from_secret_key(&secret_key)),
-        spender: Address::from(policy.gateway_address),
-        value,
-        nonce,
-        deadline,
+        owner: Address::from(owner),
+        spender: Address::from(spender),
+        value: parse_u256(&request.value)?,
+        nonce: parse_u256(&request.nonce)?,
+        deadline: parse_u256(&request.deadline)?,
     };
-    let signature =
-        sign_digest_with_secret_key(secret_key, permit.eip712_signing_hash(&domain).into())?;
-
-    Ok(EthereumPermitSignature {
-        v: signature.0,
-        r: signature.1,
-        s: signature.2,
-    })
+    Ok(permit.eip712_signing_hash(&domain).into())
 }
 
 pub fn set_policy(
@@ -351,6 +411,18 @@ pub fn sign_transaction_with_private_key(
     sign_transaction_bytes_with_secret_key(parse_private_key(private_key)?, &unsigned_transaction)
 }
 
+pub fn recover_transaction_sender(
+    request: &EthereumTransactionRequest,
+    signature: &EthereumTransactionSignature,
+) -> Result<String> {
+    let unsigned_transaction = decode_hex(&request.unsigned_transaction)?;
+    parse_unsigned_eip1559_transaction(&unsigned_transaction)?;
+    let digest = sp_core::hashing::keccak_256(&unsigned_transaction);
+    let sender =
+        recover_address_from_digest(digest, signature.y_parity, &signature.r, &signature.s)?;
+    Ok(to_checksummed_ethereum_address(&sender))
+}
+
 fn sign_transaction_bytes(
     mnemonic: &str,
     hd_path: &str,
@@ -534,6 +606,36 @@ fn sign_digest_with_secret_key(
     ))
 }
 
+fn recover_address_from_digest(digest: [u8; 32], v: u8, r: &str, s: &str) -> Result<[u8; 20]> {
+    let r = decode_hex(r)?;
+    let s = decode_hex(s)?;
+    ensure!(
+        r.len() == 32 && s.len() == 32,
+        "Ethereum signature r and s must be 32 bytes each"
+    );
+    let mut compact = [0u8; 64];
+    compact[..32].copy_from_slice(&r);
+    compact[32..].copy_from_slice(&s);
+    recover_address_from_compact(digest, v, &compact)
+}
+
+fn recover_address_from_compact(digest: [u8; 32], v: u8, compact: &[u8]) -> Result<[u8; 20]> {
+    // Accept both the legacy 27/28 encoding and a raw y-parity bit.
+    let recovery_id = match v {
+        0 | 1 => v,
+        27 | 28 => v - 27,
+        _ => anyhow::bail!("Ethereum signature has an invalid recovery id: {v}"),
+    };
+    let signature =
+        RecoverableSignature::from_compact(compact, RecoveryId::from_i32(recovery_id as i32)?)?;
+    let public_key = Secp256k1::new()
+        .recover_ecdsa(&Secp256k1Message::from_digest(digest), &signature)
+        .map_err(|_| anyhow::anyhow!("Ethereum signature could not be recovered"))?;
+    Ok(derive_ethereum_address_bytes_from_encoded_public_key(
+        &public_key.serialize_uncompressed(),
+    ))
+}
+
 fn parse_private_key(private_key: &str) -> Result<SecretKey> {
     let bytes = decode_hex(private_key)?;
     ensure!(bytes.len() == 32, "Ethereum private key must be 32 bytes");
@@ -664,4 +766,67 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn recovers_personal_message_signer_from_known_vector() {
+        let signer = recover_personal_message_signer(
+            "hello world",
+            "0x4126b820e15feace303bcd40ad56978240bf43cd435f9af2d2ee69e9797053866d4e836424774c690dfe24b59d3b07b7a2c01fb4ac5079c7eb495f0d832862031b",
+        )
+        .expect("signer should recover");
+
+        assert_eq!(signer, "0x5d2d1735e986a9e0fCBc75DE222d55D3D3B4D272");
+    }
+
+    #[test]
+    fn verifies_permit_signed_by_owner_only() {
+        let mnemonic = "test test test test test test test test test test test junk";
+        let hd_path = "m/44'/60'/0'/0'/0'";
+        let policy = EthereumSignerPolicy {
+            chain_id: 1,
+            gateway_address: [0x11; 20],
+            token_addresses: vec![[0x22; 20]],
+        };
+        let permit_request = || EthereumPermitRequest {
+            token_address: "0x2222222222222222222222222222222222222222".to_string(),
+            token_name: "Argon".to_string(),
+            value: "1000".to_string(),
+            nonce: "0".to_string(),
+            deadline: "1700000000".to_string(),
+        };
+
+        let signature = sign_permit(mnemonic, hd_path, &policy, &permit_request()).unwrap();
+        let owner = derive_address_at_path(mnemonic, hd_path).unwrap();
+        let mut request = EthereumPermitVerificationRequest {
+            chain_id: 1,
+            owner,
+            spender: "0x1111111111111111111111111111111111111111".to_string(),
+            permit: permit_request(),
+            signature,
+        };
+
+        assert!(verify_permit(&request).unwrap());
+
+        request.permit.value = "1001".to_string();
+        assert!(!verify_permit(&request).unwrap());
+    }
+
+    #[test]
+    fn recovers_transaction_sender_from_signature() {
+        let mnemonic = "test test test test test test test test test test test junk";
+        let hd_path = "m/44'/60'/0'/0'/0'";
+        let policy = EthereumSignerPolicy {
+            chain_id: 3_156_004,
+            gateway_address: [0x11; 20],
+            token_addresses: vec![],
+        };
+        let request = EthereumTransactionRequest {
+            unsigned_transaction: "0x02f8678330282480010982db70949fe46736679d2d9a65f0992f2272de9f3c7fa6e080b844095ea7b3000000000000000000000000e7f1725e7734ce288f8367e1bb143e90eeb172480000000000000000000000000000000000000000000000000000000000000001c0".to_string(),
+        };
+
+        let signature = sign_transaction(mnemonic, hd_path, &policy, &request).unwrap();
+        let sender = recover_transaction_sender(&request, &signature).unwrap();
+
+        assert_eq!(sender, derive_address_at_path(mnemonic, hd_path).unwrap());
+    }
 }
@@ -4,7 +4,7 @@ use nosleep::{NoSleep, NoSleepType};
 #[cfg(target_os = "macos")]
 use objc2_app_kit::NSWindow;
 use sp_core::Pair;
-use sp_core::crypto::Ss58Codec;
+use sp_core::crypto::{Ss58AddressFormat, Ss58Codec};
 use std::fs;
 #[cfg(any(test, all(target_os = "macos", not(debug_assertions))))]
 use std::path::Path;
@@ -28,7 +28,18 @@ mod migrations;
 mod security;
 mod ssh;
 mod ssh_access;
+mod ssh_audit;
+mod ssh_ca;
+mod ssh_compression;
+mod ssh_identity;
+mod ssh_known_hosts;
+mod ssh_operations;
 mod ssh_pool;
+mod ssh_probe;
+mod ssh_pty;
+mod ssh_sftp;
+mod ssh_sync;
+mod ssh_tunnel;
 mod troubleshooting;
 mod utils;
 mod vm;
@@ -49,17 +60,35 @@ fn is_running_from_mounted_volume(executable_path: &Path) -> bool {
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn open_ssh_connection(
     app: AppHandle,
     address: &str,
     host: &str,
     port: u16,
     username: String,
+    key_source: Option<ssh_identity::KeySource>,
+    max_concurrent_channels: Option<usize>,
+    transfer_sessions: Option<usize>,
+    jump_hosts: Option<Vec<ssh::JumpHostTarget>>,
 ) -> Result<String, String> {
     log::info!("ensure_ssh_connection");
-    let private_key =
-        security::Security::expose_private_key_openssh(&app).map_err(|e| e.to_string())?;
-    ssh_pool::open_connection(address, host, port, username, private_key)
+    let identity = ssh_identity::Identity::resolve(&app, &key_source.unwrap_or_default())
+        .await
+        .map_err(|e| e.to_string())?;
+    let known_hosts = ssh_known_hosts::KnownHosts::for_instance(&app);
+    let jump_host_configs =
+        ssh::JumpHostTarget::resolve_all(&app, jump_hosts.unwrap_or_default(), &identity)
+            .await
+            .map_err(|e| e.to_string())?;
+    let ssh_config = ssh::SSHConfig::new(host, port, username, identity, known_hosts)
+        .with_concurrency_limits(
+            max_concurrent_channels.unwrap_or(ssh::DEFAULT_MAX_CONCURRENT_CHANNELS),
+            transfer_sessions.unwrap_or(ssh::DEFAULT_TRANSFER_SESSIONS),
+        )
+        .with_jump_hosts(jump_host_configs)
+        .with_audit_log(ssh_audit::AuditLog::for_instance(&app));
+    ssh_pool::open_connection(&app, address, ssh_config)
         .await
         .map_err(|e| {
             log::error!("Error connecting to SSH: {e:#}");
@@ -70,9 +99,9 @@ async fn open_ssh_connection(
 }
 
 #[tauri::command]
-async fn close_ssh_connection(address: &str) -> Result<String, String> {
+async fn close_ssh_connection(app: AppHandle, address: &str) -> Result<String, String> {
     log::info!("close_ssh_connection");
-    ssh_pool::close_connection(address)
+    ssh_pool::close_connection(&app, address)
         .await
         .map_err(|e| e.to_string())?;
 
@@ -80,13 +109,109 @@ async fn close_ssh_connection(address: &str) -> Result<String, String> {
 }
 
 #[tauri::command]
-async fn ssh_run_command(address: &str, command: String) -> Result<(String, u32), String> {
+async fn ssh_pool_status() -> Result<Vec<ssh_pool::ConnectionStatus>, String> {
+    Ok(ssh_pool::pool_status().await)
+}
+
+#[tauri::command]
+async fn ssh_run_command(
+    address: &str,
+    command: String,
+    origin: Option<String>,
+) -> Result<(String, u32), String> {
+    ssh_operations::ensure_raw_commands_allowed()?;
+    let ssh: ssh::SSH = ssh_pool::get_connection(address)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("No SSH connection")?;
+    ssh_audit::with_origin(origin, ssh.run_command(&command))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn ssh_run_command_with_output(
+    address: &str,
+    command: String,
+    timeout_ms: Option<u64>,
+    max_output_bytes: Option<usize>,
+    origin: Option<String>,
+) -> Result<ssh::CommandOutput, String> {
+    ssh_operations::ensure_raw_commands_allowed()?;
+    let ssh: ssh::SSH = ssh_pool::get_connection(address)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("No SSH connection")?;
+    let options = ssh::CommandOptions {
+        timeout: timeout_ms.map(Duration::from_millis),
+        max_output_bytes,
+    };
+    ssh_audit::with_origin(origin, ssh.run_command_with_options(&command, options))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn ssh_run_command_streaming(
+    app: AppHandle,
+    address: &str,
+    command: String,
+    event_key: String,
+    origin: Option<String>,
+) -> Result<u32, String> {
+    ssh_operations::ensure_raw_commands_allowed()?;
+    let ssh: ssh::SSH = ssh_pool::get_connection(address)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("No SSH connection")?;
+    let cancel = ssh_pool::register_streaming_command(&event_key)
+        .await
+        .map_err(|e| e.to_string())?;
+    let streaming = ssh.run_command_streaming(
+        &command,
+        |chunk| {
+            app.emit(&event_key, chunk)?;
+            Ok(())
+        },
+        cancel,
+    );
+    let result = ssh_audit::with_origin(origin, streaming).await;
+    ssh_pool::finish_streaming_command(&event_key).await;
+    result.map_err(|e| e.to_string())
+}
+
+/// Runs a registered operation; allowed in strict mode because arguments are validated and
+/// quoted here rather than in the webview.
+#[tauri::command]
+async fn ssh_run_operation(
+    address: &str,
+    operation: String,
+    args: Option<std::collections::BTreeMap<String, serde_json::Value>>,
+    timeout_ms: Option<u64>,
+    origin: Option<String>,
+) -> Result<ssh::CommandOutput, String> {
+    let command =
+        ssh_operations::render(&operation, &args.unwrap_or_default()).map_err(|e| e.to_string())?;
     let ssh: ssh::SSH = ssh_pool::get_connection(address)
         .await
         .map_err(|e| e.to_string())?
         .ok_or("No SSH connection")?;
-    let response = ssh.run_command(&command).await.map_err(|e| e.to_string())?;
-    Ok(response)
+    let options = ssh::CommandOptions {
+        timeout: timeout_ms.map(Duration::from_millis),
+        max_output_bytes: None,
+    };
+    let origin = origin.unwrap_or(operation);
+    ssh_audit::with_origin(
+        Some(origin),
+        ssh.run_command_with_options(&command, options),
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn ssh_cancel_streaming_command(event_key: String) -> Result<bool, String> {
+    Ok(ssh_pool::cancel_streaming_command(&event_key).await)
 }
 
 #[tauri::command]
@@ -94,15 +219,21 @@ async fn ssh_upload_file(
     address: &str,
     contents: String,
     remote_path: String,
+    transfer_options: Option<ssh_sftp::TransferOptions>,
 ) -> Result<String, String> {
     log::info!("ssh_upload_file: {remote_path}");
+    ssh_operations::ensure_unrestricted_action_allowed("uploading files")?;
     let ssh: ssh::SSH = ssh_pool::get_connection(address)
         .await
         .map_err(|e| e.to_string())?
         .ok_or("No SSH connection")?;
-    ssh.upload_file(contents.as_bytes(), &remote_path)
-        .await
-        .map_err(|e| e.to_string())?;
+    ssh.upload_file(
+        contents.as_bytes(),
+        &remote_path,
+        transfer_options.unwrap_or_default(),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
     Ok("success".to_string())
 }
 
@@ -113,18 +244,27 @@ async fn ssh_download_file(
     remote_path: String,
     download_path: String,
     event_progress_key: String,
+    transfer_options: Option<ssh_sftp::TransferOptions>,
 ) -> Result<String, String> {
     log::info!("ssh_download_file: {remote_path}, {download_path}");
+    ssh_operations::ensure_unrestricted_action_allowed("downloading files")?;
     let ssh: ssh::SSH = ssh_pool::get_connection(address)
         .await
         .map_err(|e| e.to_string())?
         .ok_or("No SSH connection")?;
-    ssh.download_remote_file(&app, &remote_path, &download_path, event_progress_key)
-        .await
-        .map_err(|e| e.to_string())?;
+    ssh.download_remote_file(
+        &app,
+        &remote_path,
+        &download_path,
+        event_progress_key,
+        transfer_options.unwrap_or_else(ssh_sftp::TransferOptions::large_file),
+    )
+    .await
+    .map_err(|e| e.to_string())?;
     Ok("success".to_string())
 }
 
+/// `timeout_ms` bounds how long the upload may go without progress, not its total duration.
 #[tauri::command]
 async fn ssh_upload_embedded_file(
     app: AppHandle,
@@ -133,8 +273,10 @@ async fn ssh_upload_embedded_file(
     remote_path: String,
     event_progress_key: String,
     timeout_ms: u64,
+    transfer_options: Option<ssh_sftp::TransferOptions>,
 ) -> Result<String, String> {
     log::info!("ssh_upload_embedded_file: {local_relative_path}, {remote_path}");
+    ssh_operations::ensure_unrestricted_action_allowed("uploading files")?;
     let ssh: ssh::SSH = ssh_pool::get_connection(address)
         .await
         .map_err(|e| e.to_string())?
@@ -145,12 +287,49 @@ async fn ssh_upload_embedded_file(
         &remote_path,
         event_progress_key,
         Duration::from_millis(timeout_ms),
+        transfer_options.unwrap_or_else(ssh_sftp::TransferOptions::large_file),
     )
     .await
     .map_err(|e| e.to_string())?;
     Ok("success".to_string())
 }
 
+#[tauri::command]
+async fn ssh_sync_directory(
+    app: AppHandle,
+    address: &str,
+    local: PathBuf,
+    remote: String,
+    direction: ssh_sync::SyncDirection,
+    options: Option<ssh_sync::SyncOptions>,
+    event_progress_key: Option<String>,
+) -> Result<ssh_sync::SyncReport, String> {
+    log::info!(
+        "ssh_sync_directory: {direction:?} {}, {remote}",
+        local.display()
+    );
+    ssh_operations::ensure_unrestricted_action_allowed("syncing directories")?;
+    let ssh: ssh::SSH = ssh_pool::get_connection(address)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("No SSH connection")?;
+    ssh_sync::sync_directory(
+        &ssh,
+        &local,
+        &remote,
+        direction,
+        &options.unwrap_or_default(),
+        |progress| {
+            if let Some(event_progress_key) = &event_progress_key {
+                app.emit(event_progress_key, progress)?;
+            }
+            Ok(())
+        },
+    )
+    .await
+    .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn measure_latency(url: String) -> Result<u128, String> {
     let client = reqwest::Client::new();
@@ -307,17 +486,31 @@ async fn derive_sr25519_seed(app: AppHandle, suri: &str) -> Result<[u8; 32], Str
 
 #[tauri::command]
 async fn derive_sr25519_address(app: AppHandle, suris: Vec<String>) -> Result<Vec<String>, String> {
+    let ss58_format =
+        Ss58AddressFormat::custom(Utils::get_ss58_prefix(app.config().identifier.as_str()));
     let result = suris
         .into_iter()
         .map(|suri| {
             let (pair, _seed) = Security::sr_derive(&app, &suri).map_err(|e| e.to_string())?;
-            let address = pair.public().to_ss58check();
+            let address = pair.public().to_ss58check_with_version(ss58_format);
             Ok(address)
         })
         .collect::<Result<Vec<String>, String>>()?;
     Ok(result)
 }
 
+/// Re-encodes an address with `prefix`, defaulting to the network's prefix (18 on mainnet, 42
+/// elsewhere unless the env config sets `ARGON_SS58_PREFIX`).
+#[tauri::command]
+async fn convert_ss58_address(
+    app: AppHandle,
+    address: String,
+    prefix: Option<u16>,
+) -> Result<String, String> {
+    let prefix = prefix.unwrap_or_else(|| Utils::get_ss58_prefix(app.config().identifier.as_str()));
+    Utils::convert_ss58_address(&address, prefix).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn derive_ed25519_seed(app: AppHandle, suri: &str) -> Result<[u8; 32], String> {
     let (_pair, seed) = Security::ed_derive(&app, suri).map_err(|e| e.to_string())?;
@@ -390,6 +583,60 @@ async fn sign_ethereum_transaction(
     Ok(signed_tx)
 }
 
+#[tauri::command]
+async fn recover_ethereum_personal_signer(
+    message: String,
+    signature: String,
+) -> Result<String, String> {
+    ethereum_signer::recover_personal_message_signer(&message, &signature)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn verify_ethereum_permit(
+    request: ethereum_signer::EthereumPermitVerificationRequest,
+) -> Result<bool, String> {
+    ethereum_signer::verify_permit(&request).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn recover_transaction_sender(
+    request: ethereum_signer::EthereumTransactionRequest,
+    signature: ethereum_signer::EthereumTransactionSignature,
+) -> Result<String, String> {
+    ethereum_signer::recover_transaction_sender(&request, &signature).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn verify_sr25519_signature(
+    public_key: String,
+    message: Vec<u8>,
+    signature: Vec<u8>,
+) -> Result<bool, String> {
+    Security::verify_sr25519_signature(&public_key, &message, &signature).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn verify_ed25519_signature(
+    public_key: String,
+    message: Vec<u8>,
+    signature: Vec<u8>,
+) -> Result<bool, String> {
+    Security::verify_ed25519_signature(&public_key, &message, &signature).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn find_security_signer(
+    app: AppHandle,
+    message: Vec<u8>,
+    signature: Vec<u8>,
+) -> Result<Option<String>, String> {
+    let security = Security::load(&app).map_err(|e| e.to_string())?;
+    Ok(security
+        .find_sr25519_signer(&message, &signature)
+        .map(str::to_string))
+}
+
 #[tauri::command]
 async fn derive_x25519_public_key(app: AppHandle, suri: &str) -> Result<Vec<u8>, String> {
     let public_key = Security::derive_x25519_public_key(&app, suri).map_err(|e| e.to_string())?;
@@ -422,6 +669,86 @@ async fn decrypt_x25519_message(
     Ok(decrypted)
 }
 
+#[tauri::command]
+async fn seal_x25519_message(
+    recipient_public_key: Vec<u8>,
+    context: String,
+    payload: Vec<u8>,
+) -> Result<Vec<u8>, String> {
+    Security::seal_x25519_message(&recipient_public_key, &context, &payload)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn open_x25519_sealed_message(
+    app: AppHandle,
+    suri: &str,
+    context: String,
+    sealed_message: Vec<u8>,
+) -> Result<Vec<u8>, String> {
+    Security::open_x25519_sealed_message(&app, suri, &context, &sealed_message)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn seal_x25519_file(
+    app: AppHandle,
+    recipient_public_key: Vec<u8>,
+    context: String,
+    input_path: PathBuf,
+    output_path: PathBuf,
+    event_progress_key: Option<String>,
+) -> Result<PathBuf, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        Security::seal_x25519_file(
+            &recipient_public_key,
+            &context,
+            &input_path,
+            &output_path,
+            |percent| {
+                if let Some(event_key) = &event_progress_key {
+                    app.emit(event_key, percent)?;
+                }
+                Ok(())
+            },
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(output_path)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn open_x25519_sealed_file(
+    app: AppHandle,
+    suri: String,
+    context: String,
+    input_path: PathBuf,
+    output_path: PathBuf,
+    event_progress_key: Option<String>,
+) -> Result<PathBuf, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        Security::open_x25519_sealed_file(
+            &app,
+            &suri,
+            &context,
+            &input_path,
+            &output_path,
+            |percent| {
+                if let Some(event_key) = &event_progress_key {
+                    app.emit(event_key, percent)?;
+                }
+                Ok(())
+            },
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(output_path)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
 #[tauri::command]
 async fn derive_bitcoin_extended_key(
     app: AppHandle,
@@ -434,6 +761,22 @@ async fn derive_bitcoin_extended_key(
     Ok(bs58_key)
 }
 
+#[tauri::command]
+async fn list_derived_identities(
+    app: AppHandle,
+    index_count: Option<u32>,
+    bitcoin_vault_ids: Option<Vec<u32>>,
+    bitcoin_xpub_version: Option<u32>,
+) -> Result<Vec<security::DerivedIdentity>, String> {
+    Security::list_derived_identities(
+        &app,
+        index_count.unwrap_or(1),
+        &bitcoin_vault_ids.unwrap_or_default(),
+        bitcoin_xpub_version.unwrap_or(bip32::Prefix::XPUB.version()),
+    )
+    .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 async fn run_db_migrations(app: AppHandle) -> Result<(), String> {
     log::info!("run_db_migrations");
@@ -736,6 +1079,12 @@ pub fn run() {
     let is_test = std::env::var("CI")
         .ok()
         .is_some_and(|v| v == "true" || v == "1");
+    if std::env::var("ARGON_SSH_STRICT_MODE")
+        .ok()
+        .is_some_and(|v| v == "true" || v == "1")
+    {
+        ssh_operations::enable_strict_mode();
+    }
     let e2e_headless = std::env::var("ARGON_E2E_HEADLESS")
         .ok()
         .is_some_and(|v| v == "true" || v == "1");
@@ -853,12 +1202,11 @@ pub fn run() {
             app.manage(EthereumSignerPolicyState {
                 policy: Mutex::new(None),
             });
-            app.manage(ssh_access::SshAccessState {
-                access: Mutex::new(None),
-            });
+            app.manage(ssh_access::SshAccessState::for_instance(handle));
 
             init_config_instance_dir(handle, &relative_config_dir)?;
             tauri::async_runtime::block_on(run_db_migrations(handle.clone()))?;
+            tauri::async_runtime::spawn(ssh_access::cleanup_expired_sessions(handle.clone()));
 
             let window = app.get_webview_window("main").unwrap();
 
@@ -938,17 +1286,46 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             open_ssh_connection,
             close_ssh_connection,
+            ssh_pool_status,
             ssh_run_command,
+            ssh_run_command_with_output,
+            ssh_run_command_streaming,
+            ssh_run_operation,
+            ssh_cancel_streaming_command,
             ssh_upload_file,
             ssh_download_file,
             ssh_upload_embedded_file,
+            ssh_sync_directory,
             read_embedded_file,
             run_db_migrations,
             create_zip,
             collect_troubleshooting_os_profile,
             ssh_access::ssh_access_status,
+            ssh_access::ssh_access_list_sessions,
             ssh_access::ssh_access_activate,
+            ssh_access::ssh_access_ca_status,
+            ssh_access::ssh_access_install_ca,
+            ssh_access::ssh_access_uninstall_ca,
             ssh_access::ssh_access_deactivate,
+            ssh_access::ssh_access_list_keys,
+            ssh_access::ssh_access_revoke_stale,
+            ssh_audit::ssh_audit_query,
+            ssh_operations::ssh_list_operations,
+            ssh_operations::ssh_enable_strict_mode,
+            ssh_pty::ssh_open_pty,
+            ssh_pty::ssh_write_pty,
+            ssh_pty::ssh_resize_pty,
+            ssh_pty::ssh_close_pty,
+            ssh_probe::ssh_probe_server,
+            ssh_identity::ssh_list_agent_identities,
+            ssh_known_hosts::ssh_list_known_hosts,
+            ssh_known_hosts::ssh_inspect_known_host,
+            ssh_known_hosts::ssh_repin_known_host,
+            ssh_tunnel::ssh_open_tunnel,
+            ssh_tunnel::ssh_open_remote_tunnel,
+            ssh_tunnel::ssh_close_remote_tunnel,
+            ssh_tunnel::ssh_close_tunnel,
+            ssh_tunnel::ssh_list_tunnels,
             toggle_nosleep,
             calculate_free_space,
             vm::create_local_vm,
@@ -958,6 +1335,7 @@ pub fn run() {
             vm::find_available_port,
             derive_sr25519_seed,
             derive_sr25519_address,
+            convert_ss58_address,
             derive_ed25519_seed,
             sign_ethereum_personal_message,
             derive_ethereum_addresses,
@@ -969,10 +1347,21 @@ pub fn run() {
             sign_ethereum_transaction,
             sign_external_ethereum_transaction,
             sign_external_ethereum_personal_message,
+            recover_ethereum_personal_signer,
+            verify_ethereum_permit,
+            recover_transaction_sender,
+            verify_sr25519_signature,
+            verify_ed25519_signature,
+            find_security_signer,
             derive_x25519_public_key,
             encrypt_x25519_message,
             decrypt_x25519_message,
+            seal_x25519_message,
+            open_x25519_sealed_message,
+            seal_x25519_file,
+            open_x25519_sealed_file,
             derive_bitcoin_extended_key,
+            list_derived_identities,
             expose_mnemonic,
             export_default_ethereum_private_key,
             encrypt_wallet_secret,
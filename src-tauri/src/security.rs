@@ -1,9 +1,9 @@
-use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use aes_gcm::aead::{Aead, KeyInit, OsRng, Payload};
 use aes_gcm::{AeadCore, Aes256Gcm, Nonce};
 use anyhow::Result;
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD as BASE64;
-use bip32::{ExtendedKey, Prefix, XPrv};
+use bip32::{ExtendedKey, Prefix, XPrv, XPub};
 use curve25519_dalek::edwards::CompressedEdwardsY;
 use curve25519_dalek::montgomery::MontgomeryPoint;
 use hkdf::Hkdf;
@@ -12,11 +12,11 @@ use keyring::credential::CredentialApi;
 use secrecy::SecretString;
 use sha2::{Digest, Sha256, Sha512};
 use sp_core::crypto::AddressUri;
-use sp_core::crypto::Ss58Codec;
+use sp_core::crypto::{Ss58AddressFormat, Ss58Codec};
 use sp_core::{DeriveJunction, Pair, ed25519, sr25519};
 use std::fs;
 use std::fs::OpenOptions;
-use std::io::{ErrorKind, Write};
+use std::io::{ErrorKind, Read, Write};
 #[cfg(unix)]
 use std::os::unix::fs::OpenOptionsExt;
 use std::path::{Path, PathBuf};
@@ -30,6 +30,46 @@ use crate::{ethereum_signer, ssh::SSH, utils::Utils};
 const DEFAULT_PRIMARY_ETHEREUM_HD_PREFIX: &str = "m/44'/60'/0'/0'";
 const DEFAULT_COUNCIL_SIGNER_ETHEREUM_HD_PREFIX: &str = "m/44'/60'/1'/0'";
 const DEFAULT_MINTING_AUTHORITY_ETHEREUM_HD_PREFIX: &str = "m/44'/60'/2'/0'";
+// Bitcoin lock keys live at `{prefix}/{vaultId}'/0/{hdIndex}'` (see deriveBitcoinLockHdKey).
+const BITCOIN_LOCK_HD_PREFIX: &str = "m/1018'/0'";
+const SSH_KEY_SURI: &str = "//ssh-ed25519//1";
+const SSH_CA_KEY_SURI: &str = "//ssh-ca-ed25519//1";
+const MAX_IDENTITY_INDEXES_PER_LANE: u32 = 64;
+const X25519_SEALED_MAGIC: &[u8; 8] = b"ARGNSEAL";
+const X25519_SEALED_VERSION: u8 = 2;
+// magic + version + ephemeral public key
+const X25519_SEALED_HEADER_LEN: usize = 8 + 1 + 32;
+const X25519_STREAM_MAGIC: &[u8; 8] = b"ARGNSTRM";
+const X25519_STREAM_VERSION: u8 = 1;
+const X25519_STREAM_CHUNK_SIZE: usize = 64 * 1024;
+const X25519_STREAM_MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+// magic + version + ephemeral public key + nonce prefix + chunk size
+const X25519_STREAM_HEADER_LEN: usize = 8 + 1 + 32 + 7 + 4;
+
+/// Well-known sr25519 accounts the app derives from the mnemonic.
+const SR25519_IDENTITY_SURIS: &[(&str, &str)] = &[
+    ("miningHold", "//holding"),
+    ("miningBot", "//mining"),
+    ("vaulting", "//vaulting"),
+    ("operational", "//operational"),
+    ("vaultDelegate", "//vaulting//delegate"),
+    ("upstreamOperatorAuth", "//upstream-operator-auth"),
+    ("miningBidProxy", "//mining//proxy"),
+];
+
+/// Well-known ed25519 keys the app derives from the mnemonic, besides the indexed bootstrap
+/// endpoint secrets.
+const ED25519_IDENTITY_SURIS: &[(&str, &str)] = &[
+    ("ssh", SSH_KEY_SURI),
+    ("sshCertificateAuthority", SSH_CA_KEY_SURI),
+    ("miningSessions", "//mining//sessions"),
+    ("routerRestoreSealing", "//router-restore-sealing"),
+    ("upstreamEndpointRecovery", "//bootstrap-recovery//upstream"),
+    (
+        "ownServerEndpointRecovery",
+        "//bootstrap-recovery//own-server",
+    ),
+];
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -65,6 +105,26 @@ struct WalletMnemonicFile {
     encrypted_mnemonic: String,
 }
 
+#[derive(serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyCurve {
+    Sr25519,
+    Ed25519,
+    X25519,
+    Secp256k1,
+}
+
+#[derive(serde::Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DerivedIdentity {
+    pub name: String,
+    pub path: String,
+    pub curve: KeyCurve,
+    pub public_key: String,
+    pub address: Option<String>,
+    pub extended_public_key: Option<String>,
+}
+
 struct X25519Keypair {
     secret_key: [u8; 32],
     public_key: [u8; 32],
@@ -77,6 +137,14 @@ impl Security {
         Ok(SecretString::new(private_key.into()))
     }
 
+    /// The key that signs temporary access certificates; servers trust it via `cert-authority`.
+    pub fn expose_ssh_ca_private_key_openssh(app: &AppHandle) -> anyhow::Result<SecretString> {
+        let mnemonic = Self::expose_mnemonic(app)?;
+        let (ca_key, _seed) = Self::ed_derive_from_mnemonic(&mnemonic, SSH_CA_KEY_SURI)?;
+        let (private_key, _public_key) = SSH::format_as_openssh(ca_key)?;
+        Ok(SecretString::new(private_key.into()))
+    }
+
     fn wallet_path(app: &AppHandle) -> PathBuf {
         Utils::get_absolute_config_instance_dir(app).join("wallet.json")
     }
@@ -101,6 +169,160 @@ impl Security {
         Ok(extended_key)
     }
 
+    /// Lists every well-known key this instance derives from its mnemonic, so users can audit and
+    /// back up what the app controls. Ad-hoc SURIs requested by the frontend are not included.
+    /// Indexed keys are listed for the first `index_count` indexes of each lane, and bitcoin lock
+    /// branches for each of `bitcoin_vault_ids`.
+    pub fn list_derived_identities(
+        app: &AppHandle,
+        index_count: u32,
+        bitcoin_vault_ids: &[u32],
+        bitcoin_xpub_version: u32,
+    ) -> Result<Vec<DerivedIdentity>> {
+        let security = Self::load(app)?;
+        let mnemonic = Self::expose_mnemonic(app)?;
+        Self::derive_identities_from_mnemonic(
+            &mnemonic,
+            &security.ethereum_hd_prefixes,
+            Utils::get_ss58_prefix(app.config().identifier.as_str()),
+            index_count,
+            bitcoin_vault_ids,
+            bitcoin_xpub_version,
+        )
+    }
+
+    fn derive_identities_from_mnemonic(
+        mnemonic: &str,
+        ethereum_hd_prefixes: &EthereumHdPrefixes,
+        ss58_prefix: u16,
+        index_count: u32,
+        bitcoin_vault_ids: &[u32],
+        bitcoin_xpub_version: u32,
+    ) -> Result<Vec<DerivedIdentity>> {
+        anyhow::ensure!(
+            index_count <= MAX_IDENTITY_INDEXES_PER_LANE,
+            "Identities are limited to {MAX_IDENTITY_INDEXES_PER_LANE} indexes per lane"
+        );
+        let mut identities = Vec::new();
+
+        for (name, suri) in SR25519_IDENTITY_SURIS {
+            let (pair, _seed) = Self::sr_derive_from_mnemonic(mnemonic, suri)?;
+            identities.push(DerivedIdentity {
+                name: name.to_string(),
+                path: suri.to_string(),
+                curve: KeyCurve::Sr25519,
+                public_key: format!("0x{}", hex::encode(pair.public())),
+                address: Some(
+                    pair.public()
+                        .to_ss58check_with_version(Ss58AddressFormat::custom(ss58_prefix)),
+                ),
+                extended_public_key: None,
+            });
+        }
+
+        let bootstrap_endpoint_suris = (0..index_count).map(|index| {
+            (
+                "bootstrapEndpoint",
+                format!("//bootstrap-endpoint//{index}"),
+            )
+        });
+        let ed25519_suris = ED25519_IDENTITY_SURIS
+            .iter()
+            .map(|(name, suri)| (*name, suri.to_string()))
+            .chain(bootstrap_endpoint_suris);
+        for (name, suri) in ed25519_suris {
+            let (pair, _seed) = Self::ed_derive_from_mnemonic(mnemonic, &suri)?;
+            identities.push(DerivedIdentity {
+                name: name.to_string(),
+                path: suri,
+                curve: KeyCurve::Ed25519,
+                public_key: format!("0x{}", hex::encode(pair.public())),
+                address: None,
+                extended_public_key: None,
+            });
+        }
+
+        let encryption_suri = "//operational//encrypt";
+        let (encryption_pair, encryption_seed) =
+            Self::ed_derive_from_mnemonic(mnemonic, encryption_suri)?;
+        let encryption_keypair =
+            Self::x25519_keypair_from_ed_keypair(&encryption_pair, &encryption_seed)?;
+        identities.push(DerivedIdentity {
+            name: "operationalEncryption".to_string(),
+            path: encryption_suri.to_string(),
+            curve: KeyCurve::X25519,
+            public_key: format!("0x{}", hex::encode(encryption_keypair.public_key)),
+            address: None,
+            extended_public_key: None,
+        });
+
+        let ethereum_lanes = [
+            ("ethereumPrimary", &ethereum_hd_prefixes.primary),
+            (
+                "ethereumCouncilSigner",
+                &ethereum_hd_prefixes.council_signer,
+            ),
+            (
+                "ethereumMintingAuthority",
+                &ethereum_hd_prefixes.minting_authority,
+            ),
+        ];
+        for (name, prefix) in ethereum_lanes {
+            let hd_paths = (0..index_count)
+                .map(|index| get_ethereum_hd_path(prefix, index))
+                .collect::<Vec<_>>();
+            let derived = ethereum_signer::derive_public_identities(mnemonic, &hd_paths)?;
+            for (hd_path, (address, public_key)) in hd_paths.into_iter().zip(derived) {
+                identities.push(DerivedIdentity {
+                    name: name.to_string(),
+                    path: hd_path,
+                    curve: KeyCurve::Secp256k1,
+                    public_key,
+                    address: Some(address),
+                    extended_public_key: None,
+                });
+            }
+        }
+
+        // Lock keys are hardened children of these branches, so the xpub identifies the branch
+        // but cannot derive the keys themselves.
+        for vault_id in bitcoin_vault_ids {
+            let hd_path = format!("{BITCOIN_LOCK_HD_PREFIX}/{vault_id}'/0");
+            let (extended_public_key, public_key) =
+                Self::derive_bitcoin_extended_public_key(mnemonic, &hd_path, bitcoin_xpub_version)?;
+            identities.push(DerivedIdentity {
+                name: "bitcoinVaultLocks".to_string(),
+                path: hd_path,
+                curve: KeyCurve::Secp256k1,
+                public_key,
+                address: None,
+                extended_public_key: Some(extended_public_key),
+            });
+        }
+
+        Ok(identities)
+    }
+
+    /// Returns the serialized extended public key and its compressed public key.
+    fn derive_bitcoin_extended_public_key(
+        mnemonic: &str,
+        hd_path: &str,
+        version: u32,
+    ) -> Result<(String, String)> {
+        let prefix = Prefix::try_from(version)?;
+        anyhow::ensure!(
+            prefix.is_public(),
+            "Bitcoin identities must be listed with a public extended key version"
+        );
+        let seed = bip39::Mnemonic::from_str(mnemonic)?.to_seed("");
+        let path = bip32::DerivationPath::from_str(hd_path)?;
+        let xpub: XPub = XPrv::derive_from_path(seed, &path)?.public_key();
+        Ok((
+            xpub.to_extended_key(prefix).to_string(),
+            format!("0x{}", hex::encode(xpub.to_bytes())),
+        ))
+    }
+
     /// Get the encryption key from the OS keychain.
     /// Today this is automatic (no user prompt). In the future,
     /// this can be swapped to require biometric/password auth.
@@ -417,6 +639,303 @@ impl Security {
         )
     }
 
+    pub fn verify_sr25519_signature(
+        public_key: &str,
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<bool> {
+        let public = match decode_hex_public_key(public_key)? {
+            Some(bytes) => sr25519::Public::from_raw(bytes),
+            None => {
+                sr25519::Public::from_ss58check_with_version(public_key.trim())
+                    .map_err(|e| anyhow::anyhow!("Invalid sr25519 address: {e:?}"))?
+                    .0
+            }
+        };
+        let signature = sr25519::Signature::try_from(signature)
+            .map_err(|_| anyhow::anyhow!("sr25519 signature must be 64 bytes"))?;
+        Ok(sr25519::Pair::verify(&signature, message, &public))
+    }
+
+    pub fn verify_ed25519_signature(
+        public_key: &str,
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<bool> {
+        let public = match decode_hex_public_key(public_key)? {
+            Some(bytes) => ed25519::Public::from_raw(bytes),
+            None => {
+                ed25519::Public::from_ss58check_with_version(public_key.trim())
+                    .map_err(|e| anyhow::anyhow!("Invalid ed25519 address: {e:?}"))?
+                    .0
+            }
+        };
+        let signature = ed25519::Signature::try_from(signature)
+            .map_err(|_| anyhow::anyhow!("ed25519 signature must be 64 bytes"))?;
+        Ok(ed25519::Pair::verify(&signature, message, &public))
+    }
+
+    /// Returns the name of the account in this wallet's metadata that produced the signature.
+    pub fn find_sr25519_signer(&self, message: &[u8], signature: &[u8]) -> Option<&'static str> {
+        [
+            ("miningHoldAddress", &self.mining_hold_address),
+            ("miningBotAddress", &self.mining_bot_address),
+            ("vaultingAddress", &self.vaulting_address),
+            ("operationalAddress", &self.operational_address),
+        ]
+        .into_iter()
+        .find(|(_, address)| {
+            Self::verify_sr25519_signature(address, message, signature).unwrap_or(false)
+        })
+        .map(|(name, _)| name)
+    }
+
+    /// Encrypts a payload to a recipient with a fresh ephemeral key, so no long-term shared secret
+    /// is reused across messages. The sender stays anonymous; only the recipient can open it.
+    pub fn seal_x25519_message(
+        recipient_public_key: &[u8],
+        context: &str,
+        payload: &[u8],
+    ) -> Result<Vec<u8>> {
+        let mut ephemeral_secret_key = [0u8; 32];
+        rand::RngCore::fill_bytes(&mut rand::rng(), &mut ephemeral_secret_key);
+        Self::seal_x25519_message_with_ephemeral_key(
+            &ephemeral_secret_key,
+            recipient_public_key,
+            context,
+            payload,
+        )
+    }
+
+    pub fn open_x25519_sealed_message(
+        app: &AppHandle,
+        suri: &str,
+        context: &str,
+        sealed_message: &[u8],
+    ) -> Result<Vec<u8>> {
+        let (pair, seed) = Self::ed_derive(app, suri)?;
+        Self::open_x25519_sealed_message_from_keypair(&pair, &seed, context, sealed_message)
+    }
+
+    fn seal_x25519_message_with_ephemeral_key(
+        ephemeral_secret_key: &[u8; 32],
+        recipient_public_key: &[u8],
+        context: &str,
+        payload: &[u8],
+    ) -> Result<Vec<u8>> {
+        let recipient_public_key = Self::decode_x25519_public_key(recipient_public_key)?;
+        let ephemeral_public_key =
+            MontgomeryPoint::mul_base_clamped(*ephemeral_secret_key).to_bytes();
+        let shared_secret =
+            Self::derive_x25519_shared_secret(ephemeral_secret_key, recipient_public_key)?;
+
+        let mut header = Vec::with_capacity(X25519_SEALED_HEADER_LEN);
+        header.extend_from_slice(X25519_SEALED_MAGIC);
+        header.push(X25519_SEALED_VERSION);
+        header.extend_from_slice(&ephemeral_public_key);
+
+        let associated_data =
+            x25519_sealed_associated_data(&header, &recipient_public_key, context);
+        let encryption_key =
+            Self::derive_x25519_sealed_encryption_key(&shared_secret, &associated_data)?;
+
+        let cipher = Aes256Gcm::new_from_slice(&encryption_key)?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: payload,
+                    aad: &associated_data,
+                },
+            )
+            .map_err(|e| anyhow::anyhow!("Encryption failed: {e}"))?;
+
+        let mut sealed = header;
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    fn open_x25519_sealed_message_from_keypair(
+        pair: &ed25519::Pair,
+        seed: &[u8; 32],
+        context: &str,
+        sealed_message: &[u8],
+    ) -> Result<Vec<u8>> {
+        anyhow::ensure!(
+            sealed_message.len() >= X25519_SEALED_HEADER_LEN + 12 + 16,
+            "Sealed message is too short"
+        );
+        let (header, body) = sealed_message.split_at(X25519_SEALED_HEADER_LEN);
+        anyhow::ensure!(
+            &header[..8] == X25519_SEALED_MAGIC,
+            "Message is not an Argon sealed envelope"
+        );
+        anyhow::ensure!(
+            header[8] == X25519_SEALED_VERSION,
+            "Unsupported sealed envelope version {}",
+            header[8]
+        );
+        let ephemeral_public_key = Self::decode_x25519_public_key(&header[9..])?;
+
+        let local_keypair = Self::x25519_keypair_from_ed_keypair(pair, seed)?;
+        let shared_secret =
+            Self::derive_x25519_shared_secret(&local_keypair.secret_key, ephemeral_public_key)?;
+        let associated_data =
+            x25519_sealed_associated_data(header, &local_keypair.public_key, context);
+        let encryption_key =
+            Self::derive_x25519_sealed_encryption_key(&shared_secret, &associated_data)?;
+
+        let (nonce, ciphertext) = body.split_at(12);
+        let cipher = Aes256Gcm::new_from_slice(&encryption_key)?;
+        let plaintext = cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &associated_data,
+                },
+            )
+            .map_err(|e| anyhow::anyhow!("Decryption failed: {e}"))?;
+
+        Ok(plaintext)
+    }
+
+    /// Streams a file into a sealed envelope using STREAM-style chunking: every chunk is its own
+    /// AEAD message with a nonce of `prefix || counter || last-chunk flag`, so reordering,
+    /// truncation and appended data are all rejected on open.
+    pub fn seal_x25519_file(
+        recipient_public_key: &[u8],
+        context: &str,
+        input_path: &Path,
+        output_path: &Path,
+        on_progress: impl FnMut(u8) -> Result<()>,
+    ) -> Result<()> {
+        let mut ephemeral_secret_key = [0u8; 32];
+        rand::RngCore::fill_bytes(&mut rand::rng(), &mut ephemeral_secret_key);
+        let mut nonce_prefix = [0u8; 7];
+        rand::RngCore::fill_bytes(&mut rand::rng(), &mut nonce_prefix);
+
+        let recipient_public_key = Self::decode_x25519_public_key(recipient_public_key)?;
+        let ephemeral_public_key =
+            MontgomeryPoint::mul_base_clamped(ephemeral_secret_key).to_bytes();
+        let shared_secret =
+            Self::derive_x25519_shared_secret(&ephemeral_secret_key, recipient_public_key)?;
+
+        let mut header = Vec::with_capacity(X25519_STREAM_HEADER_LEN);
+        header.extend_from_slice(X25519_STREAM_MAGIC);
+        header.push(X25519_STREAM_VERSION);
+        header.extend_from_slice(&ephemeral_public_key);
+        header.extend_from_slice(&nonce_prefix);
+        header.extend_from_slice(&(X25519_STREAM_CHUNK_SIZE as u32).to_be_bytes());
+
+        let associated_data =
+            x25519_sealed_associated_data(&header, &recipient_public_key, context);
+        let encryption_key =
+            Self::derive_x25519_sealed_encryption_key(&shared_secret, &associated_data)?;
+        let cipher = Aes256Gcm::new_from_slice(&encryption_key)?;
+
+        let input = fs::File::open(input_path)?;
+        let total_bytes = input.metadata()?.len();
+        write_file_atomically(output_path, |output| {
+            output.write_all(&header)?;
+            stream_chunks(
+                input,
+                X25519_STREAM_CHUNK_SIZE,
+                total_bytes,
+                on_progress,
+                |chunk, counter, is_last| {
+                    let nonce = x25519_stream_nonce(&nonce_prefix, counter, is_last);
+                    let ciphertext = cipher
+                        .encrypt(Nonce::from_slice(&nonce), chunk)
+                        .map_err(|e| anyhow::anyhow!("Encryption failed: {e}"))?;
+                    output.write_all(&ciphertext)?;
+                    Ok(())
+                },
+            )
+        })
+    }
+
+    pub fn open_x25519_sealed_file(
+        app: &AppHandle,
+        suri: &str,
+        context: &str,
+        input_path: &Path,
+        output_path: &Path,
+        on_progress: impl FnMut(u8) -> Result<()>,
+    ) -> Result<()> {
+        let (pair, seed) = Self::ed_derive(app, suri)?;
+        Self::open_x25519_sealed_file_from_keypair(
+            &pair,
+            &seed,
+            context,
+            input_path,
+            output_path,
+            on_progress,
+        )
+    }
+
+    fn open_x25519_sealed_file_from_keypair(
+        pair: &ed25519::Pair,
+        seed: &[u8; 32],
+        context: &str,
+        input_path: &Path,
+        output_path: &Path,
+        on_progress: impl FnMut(u8) -> Result<()>,
+    ) -> Result<()> {
+        let mut input = fs::File::open(input_path)?;
+        let total_bytes = input.metadata()?.len();
+        let mut header = [0u8; X25519_STREAM_HEADER_LEN];
+        input
+            .read_exact(&mut header)
+            .map_err(|_| anyhow::anyhow!("Sealed file is too short"))?;
+        anyhow::ensure!(
+            &header[..8] == X25519_STREAM_MAGIC,
+            "File is not an Argon sealed stream"
+        );
+        anyhow::ensure!(
+            header[8] == X25519_STREAM_VERSION,
+            "Unsupported sealed stream version {}",
+            header[8]
+        );
+        let ephemeral_public_key = Self::decode_x25519_public_key(&header[9..41])?;
+        let mut nonce_prefix = [0u8; 7];
+        nonce_prefix.copy_from_slice(&header[41..48]);
+        let chunk_size = u32::from_be_bytes([header[48], header[49], header[50], header[51]]);
+        let chunk_size = chunk_size as usize;
+        anyhow::ensure!(
+            chunk_size > 0 && chunk_size <= X25519_STREAM_MAX_CHUNK_SIZE,
+            "Sealed stream has an invalid chunk size {chunk_size}"
+        );
+
+        let local_keypair = Self::x25519_keypair_from_ed_keypair(pair, seed)?;
+        let shared_secret =
+            Self::derive_x25519_shared_secret(&local_keypair.secret_key, ephemeral_public_key)?;
+        let associated_data =
+            x25519_sealed_associated_data(&header, &local_keypair.public_key, context);
+        let encryption_key =
+            Self::derive_x25519_sealed_encryption_key(&shared_secret, &associated_data)?;
+        let cipher = Aes256Gcm::new_from_slice(&encryption_key)?;
+
+        write_file_atomically(output_path, |output| {
+            stream_chunks(
+                input,
+                chunk_size + 16,
+                total_bytes.saturating_sub(X25519_STREAM_HEADER_LEN as u64),
+                on_progress,
+                |chunk, counter, is_last| {
+                    let nonce = x25519_stream_nonce(&nonce_prefix, counter, is_last);
+                    let plaintext = cipher
+                        .decrypt(Nonce::from_slice(&nonce), chunk)
+                        .map_err(|e| anyhow::anyhow!("Decryption failed: {e}"))?;
+                    output.write_all(&plaintext)?;
+                    Ok(())
+                },
+            )
+        })
+    }
+
     fn ed_derive_from_mnemonic(mnemonic: &str, suri: &str) -> Result<(ed25519::Pair, [u8; 32])> {
         let (pair, seed) = ed25519::Pair::from_phrase(mnemonic, None)?;
         let suri = AddressUri::parse(suri)?;
@@ -429,7 +948,7 @@ impl Security {
     }
 
     fn derive_ssh_key(mnemonic: &str) -> anyhow::Result<(String, String)> {
-        let (ssh_key, _seed) = Self::ed_derive_from_mnemonic(mnemonic, "//ssh-ed25519//1")?;
+        let (ssh_key, _seed) = Self::ed_derive_from_mnemonic(mnemonic, SSH_KEY_SURI)?;
         let (private_key, public_key) = SSH::format_as_openssh(ssh_key)?;
         Ok((private_key, public_key))
     }
@@ -552,6 +1071,17 @@ impl Security {
         Ok(key)
     }
 
+    fn derive_x25519_sealed_encryption_key(
+        shared_secret: &[u8; 32],
+        associated_data: &[u8],
+    ) -> Result<[u8; 32]> {
+        let hkdf = Hkdf::<Sha256>::new(Some(b"argon/x25519/sealed/aes256gcm/v2"), shared_secret);
+        let mut key = [0u8; 32];
+        hkdf.expand(associated_data, &mut key)
+            .map_err(|_| anyhow::anyhow!("Failed to derive an AES key from the shared secret"))?;
+        Ok(key)
+    }
+
     pub fn load(app: &AppHandle) -> Result<Self> {
         let private_key_path = Utils::get_absolute_config_instance_dir(app).join("serverkey.pem");
         if private_key_path.exists() {
@@ -647,6 +1177,128 @@ fn wallet_recovery_mnemonic(
     read_wallet_recovery_mnemonic(wallet, mnemonic_path).map(Some)
 }
 
+fn x25519_stream_nonce(prefix: &[u8; 7], counter: u32, is_last: bool) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[..7].copy_from_slice(prefix);
+    nonce[7..11].copy_from_slice(&counter.to_be_bytes());
+    nonce[11] = u8::from(is_last);
+    nonce
+}
+
+/// Reads `input` in `chunk_size` pieces, looking one chunk ahead so the final chunk is flagged.
+/// An empty input still produces a single (empty) final chunk.
+fn stream_chunks(
+    mut input: impl Read,
+    chunk_size: usize,
+    total_bytes: u64,
+    mut on_progress: impl FnMut(u8) -> Result<()>,
+    mut process_chunk: impl FnMut(&[u8], u32, bool) -> Result<()>,
+) -> Result<()> {
+    let mut current = vec![0u8; chunk_size];
+    let mut next = vec![0u8; chunk_size];
+    let mut current_len = read_chunk(&mut input, &mut current)?;
+    let mut counter = 0u32;
+    let mut processed_bytes = 0u64;
+    let mut last_percent = u8::MAX;
+
+    loop {
+        let next_len = if current_len == chunk_size {
+            read_chunk(&mut input, &mut next)?
+        } else {
+            0
+        };
+        let is_last = next_len == 0;
+        process_chunk(&current[..current_len], counter, is_last)?;
+        processed_bytes += current_len as u64;
+
+        if total_bytes > 0 {
+            let percent = ((processed_bytes.saturating_mul(100)) / total_bytes).min(100) as u8;
+            if percent != last_percent {
+                last_percent = percent;
+                on_progress(percent)?;
+            }
+        }
+
+        if is_last {
+            break;
+        }
+        std::mem::swap(&mut current, &mut next);
+        current_len = next_len;
+        counter = counter
+            .checked_add(1)
+            .ok_or_else(|| anyhow::anyhow!("Sealed stream has too many chunks"))?;
+    }
+
+    on_progress(100)
+}
+
+fn read_chunk(input: &mut impl Read, buffer: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match input.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error.into()),
+        }
+    }
+    Ok(filled)
+}
+
+/// Writes to a sibling temp file and only renames it into place once `write` succeeds, so a failed
+/// decryption never leaves unauthenticated plaintext at `path`.
+fn write_file_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut std::io::BufWriter<fs::File>) -> Result<()>,
+) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".partial");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    let result = (|| {
+        let mut writer = std::io::BufWriter::new(fs::File::create(&tmp_path)?);
+        write(&mut writer)?;
+        writer
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// Binds the envelope header (sender key), recipient key and caller context into both the key
+/// derivation and the AEAD, so a sealed message cannot be replayed under another context.
+fn x25519_sealed_associated_data(
+    header: &[u8],
+    recipient_public_key: &[u8; 32],
+    context: &str,
+) -> Vec<u8> {
+    let mut associated_data = Vec::with_capacity(header.len() + 32 + context.len());
+    associated_data.extend_from_slice(header);
+    associated_data.extend_from_slice(recipient_public_key);
+    associated_data.extend_from_slice(context.as_bytes());
+    associated_data
+}
+
+fn decode_hex_public_key(public_key: &str) -> Result<Option<[u8; 32]>> {
+    let Some(hex_value) = public_key.trim().strip_prefix("0x") else {
+        return Ok(None);
+    };
+    let mut bytes = [0u8; 32];
+    hex::decode_to_slice(hex_value, &mut bytes)
+        .map_err(|e| anyhow::anyhow!("Public key must be 32 hex-encoded bytes: {e}"))?;
+    Ok(Some(bytes))
+}
+
 fn default_ethereum_hd_prefixes() -> EthereumHdPrefixes {
     EthereumHdPrefixes {
         primary: DEFAULT_PRIMARY_ETHEREUM_HD_PREFIX.to_string(),
@@ -930,6 +1582,272 @@ mod tests {
         );
     }
 
+    #[test]
+    fn verifies_sr25519_signatures_against_wallet_accounts() {
+        let mnemonic = "test test test test test test test test test test test junk";
+        let security =
+            Security::derive_security_from_mnemonic(mnemonic).expect("security should derive");
+        let (vaulting_pair, _seed) = Security::sr_derive_from_mnemonic(mnemonic, "//vaulting")
+            .expect("vaulting key should derive");
+        let message = b"support session 42";
+        let signature = vaulting_pair.sign(message);
+
+        assert!(
+            Security::verify_sr25519_signature(
+                &security.vaulting_address,
+                message,
+                signature.as_ref()
+            )
+            .expect("signature should verify")
+        );
+        assert!(
+            !Security::verify_sr25519_signature(
+                &security.mining_bot_address,
+                message,
+                signature.as_ref()
+            )
+            .expect("signature should be checked")
+        );
+        assert_eq!(
+            security.find_sr25519_signer(message, signature.as_ref()),
+            Some("vaultingAddress")
+        );
+        assert_eq!(
+            security.find_sr25519_signer(b"other", signature.as_ref()),
+            None
+        );
+    }
+
+    #[test]
+    fn verifies_ed25519_signatures_with_hex_public_keys() {
+        let mnemonic = "test test test test test test test test test test test junk";
+        let (pair, _seed) = Security::ed_derive_from_mnemonic(mnemonic, "//chat//1")
+            .expect("ed25519 key should derive");
+        let public_key = format!("0x{}", hex::encode(pair.public()));
+        let signature = pair.sign(b"hello");
+
+        assert!(
+            Security::verify_ed25519_signature(&public_key, b"hello", signature.as_ref())
+                .expect("signature should verify")
+        );
+        assert!(
+            Security::verify_ed25519_signature(&public_key, b"hello", &[0u8; 63])
+                .expect_err("short signatures should be rejected")
+                .to_string()
+                .contains("must be 64 bytes")
+        );
+    }
+
+    #[test]
+    fn derived_identities_match_wallet_metadata() {
+        let mnemonic = "test test test test test test test test test test test junk";
+        let security =
+            Security::derive_security_from_mnemonic(mnemonic).expect("security should derive");
+
+        let identities = Security::derive_identities_from_mnemonic(
+            mnemonic,
+            &security.ethereum_hd_prefixes,
+            18,
+            2,
+            &[7],
+            bip32::Prefix::XPUB.version(),
+        )
+        .expect("identities should derive");
+
+        let find = |name: &str| {
+            identities
+                .iter()
+                .filter(|identity| identity.name == name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            find("vaulting")[0].address,
+            Some(
+                crate::utils::Utils::convert_ss58_address(&security.vaulting_address, 18)
+                    .expect("address should convert")
+            )
+        );
+        assert_eq!(find("ssh")[0].path, "//ssh-ed25519//1");
+        assert_eq!(find("miningBidProxy")[0].path, "//mining//proxy");
+        assert_eq!(find("miningSessions")[0].curve, super::KeyCurve::Ed25519);
+        assert_eq!(find("bootstrapEndpoint")[1].path, "//bootstrap-endpoint//1");
+        let primary = find("ethereumPrimary");
+        assert_eq!(primary.len(), 2);
+        assert_eq!(primary[0].path, "m/44'/60'/0'/0'/0'");
+        assert_eq!(
+            primary[0].address.as_deref(),
+            Some(security.ethereum_address.as_str())
+        );
+        assert_eq!(
+            find("ethereumMintingAuthority")[1].path,
+            "m/44'/60'/2'/0'/1'"
+        );
+        assert_eq!(find("bitcoinVaultLocks")[0].path, "m/1018'/0'/7'/0");
+        assert!(
+            find("bitcoinVaultLocks")[0]
+                .extended_public_key
+                .as_deref()
+                .is_some_and(|xpub| xpub.starts_with("xpub"))
+        );
+
+        assert!(
+            Security::derive_identities_from_mnemonic(
+                mnemonic,
+                &security.ethereum_hd_prefixes,
+                42,
+                1,
+                &[1],
+                0x0488ade4,
+            )
+            .expect_err("private extended key versions must be rejected")
+            .to_string()
+            .contains("public extended key version")
+        );
+    }
+
+    #[test]
+    fn x25519_sealed_messages_open_only_for_recipient_and_context() {
+        let (_pair, mnemonic, _seed) = sp_core::ed25519::Pair::generate_with_phrase(None);
+        let (bob_pair, bob_seed) = Security::ed_derive_from_mnemonic(&mnemonic, "//chat//2")
+            .expect("bob key should derive");
+        let (charlie_pair, charlie_seed) =
+            Security::ed_derive_from_mnemonic(&mnemonic, "//chat//3")
+                .expect("charlie key should derive");
+        let bob_public_key = Security::x25519_keypair_from_ed_keypair(&bob_pair, &bob_seed)
+            .expect("bob x25519 conversion should work")
+            .public_key;
+        let payload = b"support bundle".to_vec();
+
+        let sealed = Security::seal_x25519_message(&bob_public_key, "support", &payload)
+            .expect("message should seal");
+        let sealed_again = Security::seal_x25519_message(&bob_public_key, "support", &payload)
+            .expect("message should seal");
+        assert_ne!(
+            sealed[9..41],
+            sealed_again[9..41],
+            "each message should use a fresh ephemeral key"
+        );
+
+        assert_eq!(
+            Security::open_x25519_sealed_message_from_keypair(
+                &bob_pair, &bob_seed, "support", &sealed
+            )
+            .expect("recipient should open the message"),
+            payload
+        );
+        assert!(
+            Security::open_x25519_sealed_message_from_keypair(
+                &bob_pair, &bob_seed, "billing", &sealed
+            )
+            .is_err(),
+            "a different context must not open the message"
+        );
+        assert!(
+            Security::open_x25519_sealed_message_from_keypair(
+                &charlie_pair,
+                &charlie_seed,
+                "support",
+                &sealed
+            )
+            .is_err(),
+            "a different recipient must not open the message"
+        );
+
+        let mut tampered = sealed.clone();
+        tampered[8] = 3;
+        assert!(
+            Security::open_x25519_sealed_message_from_keypair(
+                &bob_pair, &bob_seed, "support", &tampered
+            )
+            .expect_err("unknown versions should be rejected")
+            .to_string()
+            .contains("Unsupported sealed envelope version")
+        );
+    }
+
+    #[test]
+    fn x25519_sealed_files_round_trip_and_reject_truncation() {
+        let (_pair, mnemonic, _seed) = sp_core::ed25519::Pair::generate_with_phrase(None);
+        let (bob_pair, bob_seed) = Security::ed_derive_from_mnemonic(&mnemonic, "//chat//2")
+            .expect("bob key should derive");
+        let bob_public_key = Security::x25519_keypair_from_ed_keypair(&bob_pair, &bob_seed)
+            .expect("bob x25519 conversion should work")
+            .public_key;
+
+        let test_dir = unique_test_dir("x25519-sealed-files");
+        fs::create_dir_all(&test_dir).expect("test dir should be created");
+        let input_path = test_dir.join("export.sqlite");
+        let sealed_path = test_dir.join("export.sqlite.sealed");
+        let opened_path = test_dir.join("export-opened.sqlite");
+
+        for size in [
+            0,
+            10,
+            super::X25519_STREAM_CHUNK_SIZE,
+            3 * super::X25519_STREAM_CHUNK_SIZE + 5,
+        ] {
+            let contents = (0..size).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+            fs::write(&input_path, &contents).expect("input should be written");
+
+            let mut seal_progress = Vec::new();
+            Security::seal_x25519_file(
+                &bob_public_key,
+                "logs",
+                &input_path,
+                &sealed_path,
+                |percent| {
+                    seal_progress.push(percent);
+                    Ok(())
+                },
+            )
+            .expect("file should seal");
+            assert_eq!(seal_progress.last(), Some(&100));
+
+            Security::open_x25519_sealed_file_from_keypair(
+                &bob_pair,
+                &bob_seed,
+                "logs",
+                &sealed_path,
+                &opened_path,
+                |_| Ok(()),
+            )
+            .expect("file should open");
+            assert_eq!(
+                fs::read(&opened_path).expect("opened file should exist"),
+                contents
+            );
+        }
+
+        let sealed = fs::read(&sealed_path).expect("sealed file should exist");
+        fs::write(
+            &sealed_path,
+            &sealed[..sealed.len() - super::X25519_STREAM_CHUNK_SIZE],
+        )
+        .expect("truncated file should be written");
+        fs::remove_file(&opened_path).expect("opened file should be removed");
+        assert!(
+            Security::open_x25519_sealed_file_from_keypair(
+                &bob_pair,
+                &bob_seed,
+                "logs",
+                &sealed_path,
+                &opened_path,
+                |_| Ok(()),
+            )
+            .is_err(),
+            "a truncated stream must not open"
+        );
+        assert!(!opened_path.exists(), "no partial plaintext should be left");
+        assert!(
+            fs::read_dir(&test_dir)
+                .expect("test dir should be readable")
+                .flatten()
+                .all(|entry| !entry.file_name().to_string_lossy().ends_with(".partial"))
+        );
+
+        fs::remove_dir_all(&test_dir).expect("test dir should be removed");
+    }
+
     #[test]
     fn derive_ethereum_address_matches_known_eip55_vector() {
         let mnemonic = "test test test test test test test test test test test junk";
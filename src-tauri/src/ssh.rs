@@ -1,30 +1,134 @@
+use crate::ssh_audit::{self, AuditLog};
+use crate::ssh_compression::{self, Codec, Compression};
+use crate::ssh_identity::{Identity, IdentityInfo, KeySource};
+use crate::ssh_known_hosts::{self, KnownHosts};
+use crate::ssh_sftp::{self, TransferOptions};
+use crate::ssh_tunnel;
 use crate::utils::Utils;
 use anyhow::Result;
 use log::trace;
-use russh::client::{AuthResult, Msg};
+use russh::client::Msg;
 use russh::keys::ssh_key::LineEnding;
 use russh::keys::ssh_key::private::{Ed25519Keypair, Ed25519PrivateKey};
 use russh::keys::*;
 use russh::*;
-use secrecy::{ExposeSecret, SecretString};
+use sha2::{Digest, Sha256};
 use sp_core::ed25519;
 use std::borrow::Cow;
 use std::fmt::Display;
+use std::net::SocketAddr;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter};
 use tokio::fs::File;
-use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader, BufWriter};
-use tokio::sync::Mutex;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncWriteExt, BufReader, BufWriter};
+use tokio::sync::{OwnedSemaphorePermit, RwLock, Semaphore, oneshot};
 use tokio::time::timeout;
 
 type SSHClient = client::Handle<ClientHandler>;
 
+const COMMAND_PID_MARKER: &str = "__ARGON_STREAM_PID__:";
+const PTY_TERM: &str = "xterm-256color";
+const RECONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const TRANSFER_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// OpenSSH's default MaxSessions is 10; stay below it to leave room for interactive logins.
+pub const DEFAULT_MAX_CONCURRENT_CHANNELS: usize = 8;
+pub const DEFAULT_TRANSFER_SESSIONS: usize = 2;
+// Small bookkeeping commands (rm, stat) run on the transfer client around file copies.
+const HELPER_COMMAND_OPTIONS: CommandOptions = CommandOptions {
+    timeout: Some(Duration::from_secs(30)),
+    max_output_bytes: Some(64 * 1024),
+};
+// Joining and hashing the parts of a parallel upload reads the whole file on the server.
+const ASSEMBLE_COMMAND_OPTIONS: CommandOptions = CommandOptions {
+    timeout: Some(Duration::from_secs(30 * 60)),
+    max_output_bytes: Some(64 * 1024),
+};
+const PART_PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CommandOptions {
+    pub timeout: Option<Duration>,
+    /// Per-stream cap; output beyond it is drained but dropped.
+    pub max_output_bytes: Option<usize>,
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub stdout_truncated: bool,
+    pub stderr_truncated: bool,
+    pub exit_status: Option<u32>,
+    pub exit_signal: Option<String>,
+    #[serde(rename = "durationMs", serialize_with = "serialize_duration_ms")]
+    pub duration: Duration,
+    /// Stdout and stderr interleaved in the order they arrived.
+    #[serde(skip)]
+    pub combined: String,
+    /// Stdout before lossy UTF-8 decoding, for output that carries file names.
+    #[serde(skip)]
+    pub stdout_bytes: Vec<u8>,
+}
+
+/// Returned when a command outlives `CommandOptions::timeout`. Carries the remote pid so the
+/// caller can kill what is still running on the server.
+#[derive(Debug)]
+pub struct CommandTimedOut {
+    pub timeout: Duration,
+    pub remote_pid: Option<u32>,
+}
+
+impl Display for CommandTimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SSHCommandTimedOut after {:?}", self.timeout)
+    }
+}
+
+impl std::error::Error for CommandTimedOut {}
+
+impl CommandOutput {
+    pub fn exit_code(&self) -> Result<u32> {
+        match (self.exit_status, &self.exit_signal) {
+            (Some(code), _) => Ok(code),
+            (None, Some(signal)) => anyhow::bail!("SSHCommandKilledBySignal: {signal}"),
+            (None, None) => anyhow::bail!("SSHCommandMissingExitStatus"),
+        }
+    }
+}
+
+fn serialize_duration_ms<S: serde::Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandOutputChunk {
+    pub stream: OutputStream,
+    pub data: String,
+}
+
 #[derive(Clone)]
 #[allow(clippy::upper_case_acronyms)]
 pub struct SSH {
-    client: Arc<Mutex<SSHClient>>,
-    transfer_client: Arc<Mutex<Option<SSHClient>>>,
+    // Channels are opened concurrently under the read lock; only reconnects take the write lock.
+    client: Arc<RwLock<SSHClient>>,
+    channel_permits: Arc<Semaphore>,
+    transfer_sessions: Arc<TransferSessionPool>,
     pub config: SSHConfig,
 }
 
@@ -32,8 +136,76 @@ pub struct SSH {
 pub struct SSHConfig {
     addrs: (String, u16),
     username: String,
-    private_key_openssh: SecretString,
-    public_key_openssh: String,
+    identity: Identity,
+    known_hosts: KnownHosts,
+    max_concurrent_channels: usize,
+    transfer_sessions: usize,
+    jump_hosts: Vec<JumpHost>,
+    audit_log: Option<AuditLog>,
+}
+
+/// A bastion the connection tunnels through, authenticated with its own user and key.
+#[derive(Clone)]
+pub struct JumpHost {
+    addrs: (String, u16),
+    username: String,
+    identity: Identity,
+}
+
+impl JumpHost {
+    pub fn new(host: &str, port: u16, username: String, identity: Identity) -> Self {
+        Self {
+            addrs: (host.to_string(), port),
+            username,
+            identity,
+        }
+    }
+
+    pub fn host(&self) -> String {
+        format!("{}:{}", self.addrs.0, self.addrs.1)
+    }
+}
+
+/// A jump host as the frontend describes it, before its key source is resolved.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JumpHostTarget {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub key_source: Option<KeySource>,
+}
+
+impl JumpHostTarget {
+    /// Resolves each hop's key; a hop without its own key source uses `fallback`.
+    pub async fn resolve_all(
+        app: &AppHandle,
+        targets: Vec<JumpHostTarget>,
+        fallback: &Identity,
+    ) -> Result<Vec<JumpHost>> {
+        let mut jump_hosts = Vec::new();
+        for target in targets {
+            let identity = match &target.key_source {
+                Some(key_source) => Identity::resolve(app, key_source).await?,
+                None => fallback.clone(),
+            };
+            jump_hosts.push(JumpHost::new(
+                &target.host,
+                target.port,
+                target.username,
+                identity,
+            ));
+        }
+        Ok(jump_hosts)
+    }
+}
+
+impl PartialEq for JumpHost {
+    fn eq(&self, other: &Self) -> bool {
+        self.addrs == other.addrs
+            && self.username == other.username
+            && self.identity == other.identity
+    }
 }
 
 impl SSHConfig {
@@ -41,42 +213,89 @@ impl SSHConfig {
         host: &str,
         port: u16,
         username: String,
-        private_key_openssh: SecretString,
-    ) -> Result<Self> {
+        identity: Identity,
+        known_hosts: KnownHosts,
+    ) -> Self {
         let addrs = (host.to_string(), port);
-        let public_key_openssh =
-            SSHConfig::get_pubkey_from_privkey(private_key_openssh.expose_secret())?;
 
-        Ok(SSHConfig {
+        SSHConfig {
             addrs,
             username: username.to_string(),
-            private_key_openssh,
-            public_key_openssh,
-        })
+            identity,
+            known_hosts,
+            max_concurrent_channels: DEFAULT_MAX_CONCURRENT_CHANNELS,
+            transfer_sessions: DEFAULT_TRANSFER_SESSIONS,
+            jump_hosts: Vec::new(),
+            audit_log: None,
+        }
+    }
+
+    /// Records every command run over the connection, helpers included, in `audit_log`.
+    pub fn with_audit_log(mut self, audit_log: AuditLog) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Reaches the server through `jump_hosts` in order, the first one being dialled directly.
+    pub fn with_jump_hosts(mut self, jump_hosts: Vec<JumpHost>) -> Self {
+        self.jump_hosts = jump_hosts;
+        self
+    }
+
+    pub fn with_concurrency_limits(
+        mut self,
+        max_concurrent_channels: usize,
+        transfer_sessions: usize,
+    ) -> Self {
+        self.max_concurrent_channels = max_concurrent_channels.max(1);
+        self.transfer_sessions = transfer_sessions.max(1);
+        self
     }
 
-    pub fn get_private_key(&self) -> Result<PrivateKey> {
-        let private_key = decode_secret_key(self.private_key_openssh.expose_secret(), None)?;
-        Ok(private_key)
+    pub fn identity(&self) -> &IdentityInfo {
+        self.identity.info()
     }
 
     pub fn host(&self) -> String {
         format!("{}:{}", self.addrs.0, self.addrs.1)
     }
 
-    pub fn get_pubkey_from_privkey(private_key_openssh: &str) -> Result<String> {
-        let private_key = decode_secret_key(private_key_openssh, None)?;
-        let public_key = PublicKey::from(&private_key);
-        let public_key_openssh = public_key.to_openssh()?;
-        Ok(public_key_openssh)
+    /// What the server's host key is pinned under, which includes the jump chain.
+    pub fn known_host_key(&self) -> String {
+        ssh_known_hosts::host_key(&self.addrs.0, self.addrs.1, &hop_names(&self.jump_hosts))
+    }
+
+    fn audit<E: Display>(
+        &self,
+        command: &str,
+        started_at: Instant,
+        outcome: Result<(u32, usize), E>,
+    ) {
+        if let Some(audit_log) = &self.audit_log {
+            let origin = ssh_audit::current_origin();
+            audit_log.record(
+                &self.host(),
+                command,
+                origin.as_deref(),
+                started_at,
+                outcome,
+            );
+        }
     }
 }
 
+fn hop_names(jump_hosts: &[JumpHost]) -> Vec<String> {
+    jump_hosts.iter().map(JumpHost::host).collect()
+}
+
 impl PartialEq for SSHConfig {
     fn eq(&self, other: &Self) -> bool {
         self.addrs == other.addrs
             && self.username == other.username
-            && self.public_key_openssh == other.public_key_openssh
+            && self.identity == other.identity
+            && self.max_concurrent_channels == other.max_concurrent_channels
+            && self.transfer_sessions == other.transfer_sessions
+            && self.jump_hosts == other.jump_hosts
     }
 }
 
@@ -84,8 +303,9 @@ impl SSH {
     pub async fn connect(config: &SSHConfig, timeout_duration: Duration) -> Result<Self> {
         let client = Self::connect_client(config, timeout_duration).await?;
         let ssh = SSH {
-            client: Arc::new(Mutex::new(client)),
-            transfer_client: Arc::new(Mutex::new(None)),
+            client: Arc::new(RwLock::new(client)),
+            channel_permits: Arc::new(Semaphore::new(config.max_concurrent_channels)),
+            transfer_sessions: TransferSessionPool::new(config.transfer_sessions),
             config: config.clone(),
         };
         Ok(ssh)
@@ -97,7 +317,7 @@ impl SSH {
             .map_err(|_| anyhow::anyhow!("SSH connection timed out after {timeout_duration:?}"))?
     }
 
-    async fn authenticate(ssh_config: &SSHConfig) -> Result<client::Handle<ClientHandler>> {
+    fn client_config() -> Arc<client::Config> {
         let mut config = client::Config {
             inactivity_timeout: None,
             ..<_>::default()
@@ -109,57 +329,162 @@ impl SSH {
             ]),
             ..Preferred::DEFAULT
         };
-        let config = Arc::new(config);
-        let handler = ClientHandler {};
-
-        let mut client = client::connect(config, ssh_config.addrs.clone(), handler).await?;
-        let private_key = ssh_config.get_private_key()?;
-        let private_key = Arc::new(private_key);
-        // use publickey authentication, with or without certificate
-        let auth_res = client
-            .authenticate_publickey(
-                &ssh_config.username,
-                PrivateKeyWithHashAlg::new(private_key, None),
-            )
+        Arc::new(config)
+    }
+
+    async fn authenticate(ssh_config: &SSHConfig) -> Result<client::Handle<ClientHandler>> {
+        let jump_clients =
+            Self::connect_jump_chain(&ssh_config.jump_hosts, &ssh_config.known_hosts).await?;
+        let tunnel = Self::open_hop_tunnel(jump_clients.last(), &ssh_config.addrs).await?;
+        // The target session owns the hops it runs through, so they close together.
+        let handler = ClientHandler::new(
+            ssh_config.known_host_key(),
+            Some(ssh_config.known_hosts.clone()),
+        )
+        .with_jump_clients(jump_clients);
+        let mut client = Self::connect_hop(tunnel, &ssh_config.addrs, handler).await?;
+        ssh_config
+            .identity
+            .authenticate(&mut client, &ssh_config.username)
             .await?;
+        Ok(client)
+    }
 
-        if let AuthResult::Failure {
-            remaining_methods,
-            partial_success,
-        } = auth_res
-        {
-            anyhow::bail!(
-                "Authentication (with publickey) failed for {}: {:?} (partial success: {})",
-                ssh_config.username,
-                remaining_methods,
-                partial_success
+    /// Connects and authenticates each jump host through the ones before it. Each hop's key is
+    /// pinned under the chain that reaches it.
+    async fn connect_jump_chain(
+        jump_hosts: &[JumpHost],
+        known_hosts: &KnownHosts,
+    ) -> Result<Vec<SSHClient>> {
+        let mut jump_clients: Vec<SSHClient> = Vec::new();
+        for (index, jump_host) in jump_hosts.iter().enumerate() {
+            let host_key = ssh_known_hosts::host_key(
+                &jump_host.addrs.0,
+                jump_host.addrs.1,
+                &hop_names(&jump_hosts[..index]),
             );
+            let handler = ClientHandler::new(host_key, Some(known_hosts.clone()));
+            let tunnel = Self::open_hop_tunnel(jump_clients.last(), &jump_host.addrs).await?;
+            let mut client = Self::connect_hop(tunnel, &jump_host.addrs, handler).await?;
+            jump_host
+                .identity
+                .authenticate(&mut client, &jump_host.username)
+                .await
+                .map_err(|e| e.context(format!("Jump host {}", jump_host.host())))?;
+            jump_clients.push(client);
+        }
+        Ok(jump_clients)
+    }
+
+    async fn open_hop_tunnel(
+        via: Option<&SSHClient>,
+        addrs: &(String, u16),
+    ) -> Result<Option<Channel<Msg>>> {
+        let Some(via) = via else {
+            return Ok(None);
+        };
+        let channel = via
+            .channel_open_direct_tcpip(addrs.0.clone(), u32::from(addrs.1), "127.0.0.1", 0)
+            .await?;
+        Ok(Some(channel))
+    }
+
+    /// Dials `addrs` directly, or runs the session over a tunnel through the previous hop.
+    async fn connect_hop(
+        tunnel: Option<Channel<Msg>>,
+        addrs: &(String, u16),
+        handler: ClientHandler,
+    ) -> Result<SSHClient> {
+        let host_key_error = handler.host_key_error.clone();
+        let result = match tunnel {
+            None => client::connect(Self::client_config(), addrs.clone(), handler).await,
+            Some(tunnel) => {
+                client::connect_stream(Self::client_config(), tunnel.into_stream(), handler).await
+            }
+        };
+        // Surface the typed host key error instead of russh's generic UnknownKey.
+        result.map_err(|e| take_slot(&host_key_error).unwrap_or_else(|| e.into()))
+    }
+
+    /// Completes key exchange only, returning the host key the server presents without trusting it.
+    /// The jump hosts in front of it are connected and verified as usual.
+    pub async fn fetch_host_key(
+        host: &str,
+        port: u16,
+        jump_hosts: &[JumpHost],
+        known_hosts: &KnownHosts,
+        timeout_duration: Duration,
+    ) -> Result<ssh_key::PublicKey> {
+        let addrs = (host.to_string(), port);
+        let handler = ClientHandler::new(
+            ssh_known_hosts::host_key(host, port, &hop_names(jump_hosts)),
+            None,
+        );
+        let presented_key = handler.presented_key.clone();
+        let result = timeout(timeout_duration, async {
+            let jump_clients = Self::connect_jump_chain(jump_hosts, known_hosts).await?;
+            let tunnel = Self::open_hop_tunnel(jump_clients.last(), &addrs).await?;
+            Self::connect_hop(tunnel, &addrs, handler.with_jump_clients(jump_clients)).await
+        })
+        .await
+        .map_err(|_| anyhow::anyhow!("SSH key exchange timed out after {timeout_duration:?}"))?;
+
+        match (take_slot(&presented_key), result) {
+            (Some(key), _) => Ok(key),
+            (None, Err(e)) => Err(e),
+            (None, Ok(_)) => anyhow::bail!("SSH server did not present a host key"),
         }
-        Ok(client)
     }
 
     async fn reconnect_client(client: &mut SSHClient, config: &SSHConfig) -> Result<()> {
-        *client = Self::authenticate(config).await?;
+        *client = Self::connect_client(config, RECONNECT_TIMEOUT).await?;
         Ok(())
     }
 
-    async fn get_or_connect_client<'a>(
-        client: &'a mut Option<SSHClient>,
-        config: &SSHConfig,
-        timeout_duration: Duration,
-    ) -> Result<&'a mut SSHClient> {
-        let needs_connect = match client {
-            Some(client) => client.is_closed(),
-            None => true,
-        };
+    /// Reconnects the shared session and asks the server for its remote forwards again, since
+    /// they lived on the old session.
+    async fn reconnect_shared_client(&self, client: &mut SSHClient) -> Result<()> {
+        Self::reconnect_client(client, &self.config).await?;
+        for (bind_address, remote_port) in
+            ssh_tunnel::remote_forwards_for_host(&self.config.known_host_key()).await
+        {
+            if let Err(e) = client
+                .tcpip_forward(bind_address.as_str(), u32::from(remote_port))
+                .await
+            {
+                log::warn!("Could not restore remote forward {bind_address}:{remote_port}: {e}");
+            }
+        }
+        Ok(())
+    }
 
-        if needs_connect {
-            *client = Some(Self::connect_client(config, timeout_duration).await?);
+    /// Asks the server to listen on `bind_address:remote_port` for `ssh_tunnel` and returns the
+    /// bound port, which the server picks when `remote_port` is 0.
+    pub async fn request_remote_forward(
+        &self,
+        bind_address: &str,
+        remote_port: u16,
+    ) -> Result<u16> {
+        let mut client = self.client.write().await;
+        if client.is_closed() {
+            Self::reconnect_client(&mut client, &self.config).await?;
         }
+        let bound = client
+            .tcpip_forward(bind_address, u32::from(remote_port))
+            .await?;
+        match remote_port {
+            0 => Ok(u16::try_from(bound)?),
+            port => Ok(port),
+        }
+    }
 
-        Ok(client
-            .as_mut()
-            .expect("SSH transfer client should exist after connect"))
+    pub async fn cancel_remote_forward(&self, bind_address: &str, remote_port: u16) -> Result<()> {
+        self.client
+            .read()
+            .await
+            .cancel_tcpip_forward(bind_address, u32::from(remote_port))
+            .await?;
+        Ok(())
     }
 
     async fn open_channel_on_client(
@@ -174,68 +499,372 @@ impl SSH {
         Ok(client.channel_open_session().await?)
     }
 
+    /// Opens a channel on the shared session once a slot is free. Semaphore waiters are served
+    /// in FIFO order, so a burst of slow commands cannot starve status polling.
+    async fn open_channel(&self) -> Result<(Channel<Msg>, OwnedSemaphorePermit)> {
+        let permit = self.channel_permits.clone().acquire_owned().await?;
+        Ok((self.open_channel_unmetered().await?, permit))
+    }
+
+    async fn open_channel_unmetered(&self) -> Result<Channel<Msg>> {
+        if let Ok(channel) = self.client.read().await.channel_open_session().await {
+            return Ok(channel);
+        }
+
+        let mut client = self.client.write().await;
+        // Another caller may have reconnected while this one waited for the write lock.
+        if let Ok(channel) = client.channel_open_session().await {
+            return Ok(channel);
+        }
+        self.reconnect_shared_client(&mut client).await?;
+        Ok(client.channel_open_session().await?)
+    }
+
+    /// Starts a login shell on a pseudo-terminal. Shells stay open for as long as the user works
+    /// in them, so like forwarded channels they do not hold a channel permit.
+    pub async fn open_pty(&self, cols: u32, rows: u32) -> Result<Channel<Msg>> {
+        let channel = self.open_channel_unmetered().await?;
+        channel
+            .request_pty(true, PTY_TERM, cols, rows, 0, 0, &[])
+            .await?;
+        channel.request_shell(true).await?;
+        Ok(channel)
+    }
+
+    /// Opens a `direct-tcpip` channel to `host:port` as seen from the server. Forwarded channels
+    /// are not sessions, so they do not count against `MaxSessions` or the channel permits.
+    pub async fn open_direct_tcpip(
+        &self,
+        host: &str,
+        port: u16,
+        originator: SocketAddr,
+    ) -> Result<Channel<Msg>> {
+        let originator_host = originator.ip().to_string();
+        let originator_port = u32::from(originator.port());
+        {
+            let client = self.client.read().await;
+            if !client.is_closed() {
+                return Ok(client
+                    .channel_open_direct_tcpip(
+                        host,
+                        u32::from(port),
+                        originator_host,
+                        originator_port,
+                    )
+                    .await?);
+            }
+        }
+
+        let mut client = self.client.write().await;
+        // Another caller may have reconnected while this one waited for the write lock.
+        if client.is_closed() {
+            self.reconnect_shared_client(&mut client).await?;
+        }
+        Ok(client
+            .channel_open_direct_tcpip(host, u32::from(port), originator_host, originator_port)
+            .await?)
+    }
+
     async fn run_command_on_client(
         client: &mut SSHClient,
         config: &SSHConfig,
         command: String,
-    ) -> Result<(String, u32)> {
-        let final_command = command.to_string().replace('\'', "'\\''");
-        trace!("Executing ssh command: {final_command}");
-        let shell_command = format!("bash -c '{final_command}'");
-        let mut channel = Self::open_channel_on_client(client, config).await?;
-        channel.exec(true, shell_command).await?;
+        options: CommandOptions,
+    ) -> Result<CommandOutput> {
+        let channel = Self::open_channel_on_client(client, config).await?;
+        let result = Self::run_command_on_channel(channel, config, command, options).await;
+        if let Some(pid) = timed_out_pid(&result) {
+            let kill = async {
+                let channel = Self::open_channel_on_client(client, config).await?;
+                Self::run_command_on_channel(
+                    channel,
+                    config,
+                    kill_command(pid),
+                    HELPER_COMMAND_OPTIONS,
+                )
+                .await
+            };
+            if let Err(e) = kill.await {
+                log::warn!("Failed to kill timed out ssh command {pid}: {e:#}");
+            }
+        }
+        result
+    }
+
+    async fn run_command_on_channel(
+        channel: Channel<Msg>,
+        config: &SSHConfig,
+        command: String,
+        options: CommandOptions,
+    ) -> Result<CommandOutput> {
+        let started_at = Instant::now();
+        let result = Self::collect_command_output(channel, &command, options).await;
+        config.audit(
+            &command,
+            started_at,
+            result
+                .as_ref()
+                .map_err(|e| format!("{e:#}"))
+                .and_then(|output| {
+                    let exit_code = output.exit_code().map_err(|e| e.to_string())?;
+                    Ok((exit_code, output.stdout.len() + output.stderr.len()))
+                }),
+        );
+        result
+    }
+
+    async fn collect_command_output(
+        mut channel: Channel<Msg>,
+        command: &str,
+        options: CommandOptions,
+    ) -> Result<CommandOutput> {
+        trace!("Executing ssh command: {command}");
+        let started_at = Instant::now();
+        channel.exec(true, pid_reporting_script(command)).await?;
+        channel.eof().await?;
+
+        let mut pid_marker = PidMarkerReader::default();
+        let mut stdout = LimitedOutput::new(options.max_output_bytes);
+        let mut stderr = LimitedOutput::new(options.max_output_bytes);
+        let mut combined =
+            LimitedOutput::new(options.max_output_bytes.map(|n| n.saturating_mul(2)));
+        let mut exit_status = None;
+        let mut exit_signal = None;
+
+        let collect = async {
+            // There's an event available on the session channel
+            while let Some(msg) = channel.wait().await {
+                match msg {
+                    russh::ChannelMsg::Data { ref data } => {
+                        let data = pid_marker.feed(data);
+                        stdout.push(&data);
+                        combined.push(&data);
+                    }
+                    // 1 is stderr
+                    russh::ChannelMsg::ExtendedData { ref data, ext: 1 } => {
+                        stderr.push(data);
+                        combined.push(data);
+                    }
+                    // The command has returned an exit code
+                    russh::ChannelMsg::ExitStatus { exit_status: code } => {
+                        exit_status = Some(code);
+                    }
+                    // The command was terminated by a signal, so no exit status will follow
+                    russh::ChannelMsg::ExitSignal { signal_name, .. } => {
+                        exit_signal = Some(signal_display_name(&signal_name));
+                    }
+                    _ => {}
+                }
+            }
+        };
+        let timed_out = match options.timeout {
+            Some(timeout_duration) => timeout(timeout_duration, collect).await.is_err(),
+            None => {
+                collect.await;
+                false
+            }
+        };
+        let _ = channel.close().await;
+
+        if timed_out {
+            return Err(CommandTimedOut {
+                timeout: options.timeout.unwrap_or_default(),
+                remote_pid: pid_marker.pid,
+            }
+            .into());
+        }
+        let unterminated = pid_marker.finish();
+        stdout.push(&unterminated);
+        combined.push(&unterminated);
+
+        Ok(CommandOutput {
+            stdout: stdout.text(),
+            stderr: stderr.text(),
+            stdout_truncated: stdout.truncated,
+            stderr_truncated: stderr.truncated,
+            exit_status,
+            exit_signal,
+            duration: started_at.elapsed(),
+            combined: combined.text(),
+            stdout_bytes: stdout.bytes,
+        })
+    }
+
+    pub async fn run_command(&self, command: impl Display) -> Result<(String, u32)> {
+        let output = self
+            .run_command_with_options(command, CommandOptions::default())
+            .await?;
+        let code = output.exit_code()?;
+        Ok((output.combined, code))
+    }
+
+    /// Runs a command and keeps stdout, stderr and how it exited apart.
+    pub async fn run_command_with_options(
+        &self,
+        command: impl Display,
+        options: CommandOptions,
+    ) -> Result<CommandOutput> {
+        let (channel, _permit) = self.open_channel().await?;
+        let result =
+            Self::run_command_on_channel(channel, &self.config, command.to_string(), options).await;
+        if let Some(pid) = timed_out_pid(&result) {
+            self.kill_remote_process(pid).await;
+        }
+        result
+    }
+
+    /// Runs a command while passing stdout and stderr chunks to `on_output` as they arrive.
+    /// Sending on `cancel` terminates the remote process group.
+    pub async fn run_command_streaming(
+        &self,
+        command: impl Display,
+        mut on_output: impl FnMut(CommandOutputChunk) -> Result<()>,
+        cancel: oneshot::Receiver<()>,
+    ) -> Result<u32> {
+        let command = command.to_string();
+        let started_at = Instant::now();
+        let mut output_bytes = 0;
+        let on_output = |chunk: CommandOutputChunk| {
+            output_bytes += chunk.data.len();
+            on_output(chunk)
+        };
+        let result = self.stream_command(&command, on_output, cancel).await;
+        self.config.audit(
+            &command,
+            started_at,
+            result
+                .as_ref()
+                .map(|exit_code| (*exit_code, output_bytes))
+                .map_err(|e| format!("{e:#}")),
+        );
+        result
+    }
+
+    async fn stream_command(
+        &self,
+        command: &str,
+        mut on_output: impl FnMut(CommandOutputChunk) -> Result<()>,
+        mut cancel: oneshot::Receiver<()>,
+    ) -> Result<u32> {
+        trace!("Executing streaming ssh command: {command}");
+        let (mut channel, _permit) = self.open_channel().await?;
+        channel.exec(true, pid_reporting_script(command)).await?;
         channel.eof().await?;
 
+        let mut pid_marker = PidMarkerReader::default();
+        let mut stdout = Utf8ChunkDecoder::default();
+        let mut stderr = Utf8ChunkDecoder::default();
+        let mut emit = |stream, data: String| {
+            if data.is_empty() {
+                return Ok(());
+            }
+            on_output(CommandOutputChunk { stream, data })
+        };
         let mut code = None;
-        let mut output = String::new();
+        let mut signal = None;
+        let mut cancelled = false;
 
         loop {
-            // There's an event available on the session channel
-            let Some(msg) = channel.wait().await else {
+            let msg = tokio::select! {
+                msg = channel.wait() => msg,
+                Ok(()) = &mut cancel, if !cancelled => {
+                    cancelled = true;
+                    self.kill_streaming_command(&channel, pid_marker.pid).await;
+                    continue;
+                }
+            };
+            let Some(msg) = msg else {
                 break;
             };
             match msg {
-                // Collect stdout data
                 russh::ChannelMsg::Data { ref data } => {
-                    output.push_str(&String::from_utf8_lossy(data));
+                    let data = pid_marker.feed(data);
+                    emit(OutputStream::Stdout, stdout.decode(&data))?;
                 }
                 russh::ChannelMsg::ExtendedData { ref data, ext } => {
                     if ext == 1 {
-                        // 1 is stderr
-                        output.push_str(&String::from_utf8_lossy(data));
+                        emit(OutputStream::Stderr, stderr.decode(data))?;
                     }
                 }
-                // The command has returned an exit code
                 russh::ChannelMsg::ExitStatus { exit_status } => {
                     code = Some(exit_status);
                 }
+                russh::ChannelMsg::ExitSignal { signal_name, .. } => {
+                    signal = Some(signal_display_name(&signal_name));
+                }
                 _ => {}
             }
         }
         let _ = channel.close().await;
-        let code = code.ok_or_else(|| anyhow::anyhow!("SSHCommandMissingExitStatus"))?;
 
-        Ok((output, code))
+        let unterminated = pid_marker.finish();
+        emit(OutputStream::Stdout, stdout.decode(&unterminated))?;
+        emit(OutputStream::Stdout, stdout.finish())?;
+        emit(OutputStream::Stderr, stderr.finish())?;
+
+        if cancelled {
+            anyhow::bail!("SSHCommandCancelled");
+        }
+        match (code, signal) {
+            (Some(code), _) => Ok(code),
+            (None, Some(signal)) => anyhow::bail!("SSHCommandKilledBySignal: {signal}"),
+            (None, None) => anyhow::bail!("SSHCommandMissingExitStatus"),
+        }
     }
 
-    pub async fn run_command(&self, command: impl Display) -> Result<(String, u32)> {
-        let mut client = self.client.lock().await;
-        Self::run_command_on_client(&mut client, &self.config, command.to_string()).await
+    async fn kill_streaming_command(&self, channel: &Channel<Msg>, remote_pid: Option<u32>) {
+        if let Some(pid) = remote_pid {
+            log::info!("Cancelling remote ssh command with pid {pid}");
+            self.kill_remote_process(pid).await;
+        }
+        let _ = channel.signal(Sig::TERM).await;
+        let _ = channel.close().await;
+    }
+
+    async fn kill_remote_process(&self, pid: u32) {
+        // Bypass the concurrency cap so a kill is never queued behind the command it stops.
+        let result = match self.open_channel_unmetered().await {
+            Ok(kill_channel) => {
+                Self::run_command_on_channel(
+                    kill_channel,
+                    &self.config,
+                    kill_command(pid),
+                    HELPER_COMMAND_OPTIONS,
+                )
+                .await
+            }
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            log::warn!("Failed to kill remote ssh command {pid}: {e:#}");
+        }
     }
 
-    pub async fn upload_file(&self, contents: &[u8], remote_path: &str) -> Result<()> {
+    pub async fn upload_file(
+        &self,
+        contents: &[u8],
+        remote_path: &str,
+        options: TransferOptions,
+    ) -> Result<()> {
         let contents = contents.to_vec();
         let remote_path = remote_path.to_string();
-        let transfer_client = self.transfer_client.clone();
+        let transfer_sessions = self.transfer_sessions.clone();
         let config = self.config.clone();
-        let timeout_duration = Duration::from_secs(10);
+        // The worker thread doesn't inherit the task-local audit origin.
+        let origin = ssh_audit::current_origin();
         tauri::async_runtime::spawn_blocking(move || -> Result<()> {
-            tauri::async_runtime::block_on(async move {
-                let mut client = transfer_client.lock().await;
-                let client =
-                    Self::get_or_connect_client(&mut client, &config, timeout_duration).await?;
-                Self::upload_file_on_client(client, &config, &contents, &remote_path).await
-            })
+            tauri::async_runtime::block_on(ssh_audit::with_origin(origin, async move {
+                let mut session = transfer_sessions.checkout(&config).await?;
+                let result = Self::upload_file_on_client(
+                    session.client(),
+                    &config,
+                    &contents,
+                    &remote_path,
+                    &options,
+                )
+                .await;
+                session.finish(result)
+            }))
         })
         .await
         .map_err(|e| anyhow::anyhow!("SSH worker thread failed: {e}"))?
@@ -246,126 +875,757 @@ impl SSH {
         config: &SSHConfig,
         contents: &[u8],
         remote_path: &str,
+        options: &TransferOptions,
     ) -> Result<()> {
-        let escaped_remote = shell_escape_remote_path(remote_path);
-        let mut channel = Self::open_channel_on_client(client, config).await?;
-        let scp_command = format!("cat > {escaped_remote}");
-        channel.exec(true, scp_command).await?;
-
-        // Write the contents of the setup script
-        channel.data(contents).await?;
-        channel.eof().await?;
-
-        // Wait for the copy to complete
-        while channel.wait().await.is_some() {}
+        let mut source = std::io::Cursor::new(contents);
+        let throttle = ssh_sftp::RateLimiter::for_options(options);
+        if let Some(codec) =
+            Self::negotiate_codec_on_client(client, config, options.compression).await?
+        {
+            return Self::upload_compressed_on_client(
+                client,
+                config,
+                &mut source,
+                contents.len() as u64,
+                remote_path,
+                codec,
+                options,
+                throttle.as_deref(),
+                &mut |_, _, _| Ok(()),
+            )
+            .await;
+        }
+        if Self::sftp_upload_on_client(
+            client,
+            config,
+            &mut source,
+            contents.len() as u64,
+            remote_path,
+            options,
+            throttle.as_deref(),
+            &mut |_, _| Ok(()),
+        )
+        .await?
+        {
+            return Ok(());
+        }
 
-        Ok(())
+        Self::cat_upload_on_client(
+            client,
+            config,
+            &mut source,
+            contents.len() as u64,
+            remote_path,
+            options,
+            None,
+            &mut |_, _| Ok(()),
+        )
+        .await
     }
 
+    /// Fails once no bytes have moved for `stall_timeout`, however long the whole upload takes.
     pub async fn upload_embedded_file(
         &self,
         app: &AppHandle,
         file_name: &str,
         remote_path: &str,
         event_progress_key: String,
-        timeout_duration: Duration,
+        stall_timeout: Duration,
+        options: TransferOptions,
     ) -> Result<()> {
         let app = app.clone();
         let file_name = file_name.to_string();
         let remote_path = remote_path.to_string();
-        let transfer_client = self.transfer_client.clone();
+        let transfer_sessions = self.transfer_sessions.clone();
         let config = self.config.clone();
+        // The worker thread doesn't inherit the task-local audit origin.
+        let origin = ssh_audit::current_origin();
         tauri::async_runtime::spawn_blocking(move || -> Result<()> {
-            tauri::async_runtime::block_on(async move {
-                let mut client = transfer_client.lock().await;
-                let result = match timeout(timeout_duration, async {
-                    let client =
-                        Self::get_or_connect_client(&mut client, &config, Duration::from_secs(10))
-                            .await?;
-                    Self::upload_embedded_file_on_client(
-                        client,
+            tauri::async_runtime::block_on(ssh_audit::with_origin(origin, async move {
+                let path = Utils::get_embedded_path(&app, &file_name)?;
+                let mut progress = ProgressEmitter::new(&app, &event_progress_key);
+                let watchdog = ssh_sftp::StallWatchdog::default();
+                let transfer = async {
+                    if options.compression != Compression::None {
+                        let mut session = transfer_sessions.checkout(&config).await?;
+                        let result = Self::upload_local_file_compressed_on_client(
+                            session.client(),
+                            &config,
+                            &path,
+                            &remote_path,
+                            &options,
+                            &mut progress,
+                            &watchdog,
+                        )
+                        .await;
+                        if session.finish(result)? {
+                            return Ok(());
+                        }
+                    }
+                    Self::upload_local_file_pooled(
+                        &transfer_sessions,
                         &config,
-                        &app,
-                        &file_name,
+                        &path,
                         &remote_path,
-                        &event_progress_key,
+                        &options,
+                        &mut |transferred, total| {
+                            watchdog.record(transferred, total);
+                            progress.update(transferred, total)
+                        },
                     )
                     .await
-                })
-                .await
-                {
-                    Ok(result) => result,
-                    Err(_) => Err(anyhow::anyhow!(
-                        "SSH upload timed out after {timeout_duration:?}"
-                    )),
                 };
-
-                if result.is_err() {
-                    *client = None;
-                }
-
-                result
-            })
+                // Sessions dropped on a stall are discarded rather than returned to the pool.
+                ssh_sftp::with_stall_timeout(stall_timeout, &watchdog, transfer).await?;
+                progress.finish()
+            }))
         })
         .await
         .map_err(|e| anyhow::anyhow!("SSH worker thread failed: {e}"))?
     }
 
-    async fn upload_embedded_file_on_client(
-        client: &mut SSHClient,
+    /// Uploads a local file on pooled transfer sessions, reporting `(transferred, total)` bytes.
+    pub async fn upload_local_file(
+        &self,
+        local_path: &Path,
+        remote_path: &str,
+        options: &TransferOptions,
+        mut on_progress: impl FnMut(u64, u64) -> Result<()>,
+    ) -> Result<()> {
+        Self::upload_local_file_pooled(
+            &self.transfer_sessions,
+            &self.config,
+            local_path,
+            remote_path,
+            options,
+            &mut on_progress,
+        )
+        .await
+    }
+
+    /// Splits the file across several transfer sessions when it is large enough and
+    /// `options.parallel_parts` allows, otherwise uploads it on one session.
+    async fn upload_local_file_pooled(
+        pool: &Arc<TransferSessionPool>,
         config: &SSHConfig,
-        app: &AppHandle,
-        file_name: &str,
+        local_path: &Path,
         remote_path: &str,
-        event_progress_key: &str,
+        options: &TransferOptions,
+        on_progress: &mut impl FnMut(u64, u64) -> Result<()>,
     ) -> Result<()> {
-        let path = Utils::get_embedded_path(app, file_name)?;
-        let file = File::open(&path).await?;
+        let throttle = ssh_sftp::RateLimiter::for_options(options);
+        let total = tokio::fs::metadata(local_path).await?.len();
+        // Parts beyond the pool size would only queue for a session.
+        let max_parts = options.parallel_parts.min(config.transfer_sessions);
+        let parts = ssh_sftp::plan_parts(total, max_parts);
+        if parts.len() > 1
+            && Self::upload_parts(
+                pool,
+                config,
+                local_path,
+                remote_path,
+                parts,
+                options,
+                throttle.clone(),
+                on_progress,
+            )
+            .await?
+        {
+            return Ok(());
+        }
 
-        let escaped_remote = shell_escape_remote_path(remote_path);
-        // ensure old file is removed
-        let _ =
-            Self::run_command_on_client(client, config, format!("rm -f {escaped_remote}")).await;
+        let mut session = pool.checkout(config).await?;
+        let result = Self::upload_local_file_on_client(
+            session.client(),
+            config,
+            local_path,
+            remote_path,
+            options,
+            throttle.as_deref(),
+            on_progress,
+        )
+        .await;
+        session.finish(result)
+    }
 
-        let mut channel = Self::open_channel_on_client(client, config).await?;
-        channel
-            .exec(true, format!("cat > {escaped_remote}"))
-            .await?;
-        let mut writer = channel.make_writer();
+    /// Sends each part to its own remote file on a separate session, then joins them on the
+    /// server. Returns `Ok(false)` when SFTP cannot be used and the caller should upload in one
+    /// piece.
+    #[allow(clippy::too_many_arguments)]
+    async fn upload_parts(
+        pool: &Arc<TransferSessionPool>,
+        config: &SSHConfig,
+        local_path: &Path,
+        remote_path: &str,
+        parts: Vec<Range<u64>>,
+        options: &TransferOptions,
+        throttle: Option<Arc<ssh_sftp::RateLimiter>>,
+        on_progress: &mut impl FnMut(u64, u64) -> Result<()>,
+    ) -> Result<bool> {
+        let Some(target) = ssh_sftp::remote_path(remote_path) else {
+            return Ok(false);
+        };
+        let mut session = pool.checkout(config).await?;
+        let sftp_available = async {
+            let channel = Self::open_channel_on_client(session.client(), config).await?;
+            let sftp = ssh_sftp::open_session(channel).await?;
+            if let Some(sftp) = &sftp {
+                let _ = sftp.close().await;
+            }
+            Ok(sftp.is_some())
+        }
+        .await;
+        if !session.finish(sftp_available)? {
+            log::info!(
+                "SFTP unavailable on {}, uploading in one piece",
+                config.host()
+            );
+            return Ok(false);
+        }
 
+        let total = parts.last().map_or(0, |part| part.end);
+        let part_count = parts.len();
+        log::info!("Uploading {remote_path} in {part_count} parts");
+        let transferred = Arc::new(AtomicU64::new(0));
+        let mut tasks = tokio::task::JoinSet::new();
+        for (index, range) in parts.into_iter().enumerate() {
+            let pool = pool.clone();
+            let config = config.clone();
+            let local_path = local_path.to_path_buf();
+            let part_path = format!("{target}{}.{index}", ssh_sftp::REMOTE_PARTIAL_SUFFIX);
+            let resume = options.resume;
+            let throttle = throttle.clone();
+            let transferred = transferred.clone();
+            tasks.spawn(async move {
+                let mut session = pool.checkout(&config).await?;
+                let result = async {
+                    let mut source = File::open(&local_path).await?;
+                    let channel = Self::open_channel_on_client(session.client(), &config).await?;
+                    let sftp = ssh_sftp::open_session(channel)
+                        .await?
+                        .ok_or_else(|| anyhow::anyhow!("SFTP unavailable on {}", config.host()))?;
+                    let mut reported = 0;
+                    let uploaded = ssh_sftp::upload_part(
+                        &sftp,
+                        &mut source,
+                        &part_path,
+                        range,
+                        resume,
+                        throttle.as_deref(),
+                        &mut |part_transferred, _| {
+                            transferred.fetch_add(part_transferred - reported, Ordering::Relaxed);
+                            reported = part_transferred;
+                            Ok(())
+                        },
+                    )
+                    .await;
+                    let _ = sftp.close().await;
+                    uploaded
+                }
+                .await;
+                session.finish(result)
+            });
+        }
+
+        // Dropping the set on an early return aborts the remaining parts.
+        loop {
+            tokio::select! {
+                joined = tasks.join_next() => match joined {
+                    Some(joined) => joined??,
+                    None => break,
+                },
+                _ = tokio::time::sleep(PART_PROGRESS_INTERVAL) => {}
+            }
+            on_progress(transferred.load(Ordering::Relaxed), total)?;
+        }
+
+        let mut session = pool.checkout(config).await?;
+        let result = Self::assemble_parts_on_client(
+            session.client(),
+            config,
+            local_path,
+            remote_path,
+            part_count,
+            options,
+        )
+        .await;
+        session.finish(result)?;
+        Ok(true)
+    }
+
+    /// Concatenates the uploaded parts into the partial file, verifies it against the local
+    /// file and renames it into place.
+    async fn assemble_parts_on_client(
+        client: &mut SSHClient,
+        config: &SSHConfig,
+        local_path: &Path,
+        remote_path: &str,
+        part_count: usize,
+        options: &TransferOptions,
+    ) -> Result<()> {
+        let partial_shell_path = format!("{remote_path}{}", ssh_sftp::REMOTE_PARTIAL_SUFFIX);
+        let escaped_partial = shell_escape_remote_path(&partial_shell_path);
+        let escaped_parts = (0..part_count)
+            .map(|index| shell_escape_remote_path(&format!("{partial_shell_path}.{index}")))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let output = Self::run_command_on_client(
+            client,
+            config,
+            format!("cat {escaped_parts} > {escaped_partial} && rm -f {escaped_parts}"),
+            ASSEMBLE_COMMAND_OPTIONS,
+        )
+        .await?;
+        if output.exit_code()? != 0 {
+            anyhow::bail!(
+                "SSHUploadAssemblyFailed: {remote_path}: {}",
+                output.stderr.trim()
+            );
+        }
+
+        if options.verify_sha256 {
+            let mut file = File::open(local_path).await?;
+            let total = file.metadata().await?.len();
+            let local_hash = ssh_sftp::sha256_of(&mut file, total).await?;
+            let output = Self::run_command_on_client(
+                client,
+                config,
+                sha256_command(&partial_shell_path),
+                ASSEMBLE_COMMAND_OPTIONS,
+            )
+            .await?;
+            let remote_hash = parse_remote_sha256(&output)?;
+            if remote_hash != local_hash {
+                let _ = Self::run_command_on_client(
+                    client,
+                    config,
+                    format!("rm -f {escaped_partial}"),
+                    HELPER_COMMAND_OPTIONS,
+                )
+                .await;
+                anyhow::bail!(
+                    "SSHChecksumMismatch: {remote_path} is {remote_hash} on the server, expected {local_hash}"
+                );
+            }
+        }
+
+        Self::commit_partial_on_client(client, config, remote_path, options).await
+    }
+
+    /// Moves the partial file of `remote_path` into place, applying the requested attributes
+    /// over SFTP. Servers without SFTP only get the move.
+    async fn commit_partial_on_client(
+        client: &mut SSHClient,
+        config: &SSHConfig,
+        remote_path: &str,
+        options: &TransferOptions,
+    ) -> Result<()> {
+        if let Some(target) = ssh_sftp::remote_path(remote_path) {
+            let channel = Self::open_channel_on_client(client, config).await?;
+            if let Some(sftp) = ssh_sftp::open_session(channel).await? {
+                let partial = format!("{target}{}", ssh_sftp::REMOTE_PARTIAL_SUFFIX);
+                let result =
+                    ssh_sftp::apply_upload_attributes(&sftp, &partial, &target, options).await;
+                let _ = sftp.close().await;
+                result?;
+                return Self::move_partial_into_place_on_client(client, config, remote_path).await;
+            }
+        }
+        let partial_shell_path = format!("{remote_path}{}", ssh_sftp::REMOTE_PARTIAL_SUFFIX);
+        Self::apply_attributes_with_shell_on_client(client, config, &partial_shell_path, options)
+            .await?;
+        Self::move_partial_into_place_on_client(client, config, remote_path).await
+    }
+
+    /// The `cat` fallback's counterpart to `ssh_sftp::apply_upload_attributes`, for the
+    /// requested attributes only.
+    async fn apply_attributes_with_shell_on_client(
+        client: &mut SSHClient,
+        config: &SSHConfig,
+        remote_path: &str,
+        options: &TransferOptions,
+    ) -> Result<()> {
+        let Some(command) = attribute_command(remote_path, options) else {
+            return Ok(());
+        };
+        let output =
+            Self::run_command_on_client(client, config, command, HELPER_COMMAND_OPTIONS).await?;
+        if output.exit_code()? != 0 {
+            anyhow::bail!(
+                "Setting attributes of {remote_path} failed: {}",
+                output.stderr.trim()
+            );
+        }
+        Ok(())
+    }
+
+    /// `mv -f` replaces the target in a single rename, where SFTP v3 rename refuses to
+    /// overwrite and would need the target removed first.
+    async fn move_partial_into_place_on_client(
+        client: &mut SSHClient,
+        config: &SSHConfig,
+        remote_path: &str,
+    ) -> Result<()> {
+        let partial_shell_path = format!("{remote_path}{}", ssh_sftp::REMOTE_PARTIAL_SUFFIX);
+        let output = Self::run_command_on_client(
+            client,
+            config,
+            format!(
+                "mv -f -- {} {}",
+                shell_escape_remote_path(&partial_shell_path),
+                shell_escape_remote_path(remote_path)
+            ),
+            HELPER_COMMAND_OPTIONS,
+        )
+        .await?;
+        if output.exit_code()? != 0 {
+            anyhow::bail!(
+                "SSHUploadCommitFailed: {remote_path}: {}",
+                output.stderr.trim()
+            );
+        }
+        Ok(())
+    }
+
+    /// Asks the server which codecs it has. `None` means the transfer goes uncompressed.
+    async fn negotiate_codec_on_client(
+        client: &mut SSHClient,
+        config: &SSHConfig,
+        compression: Compression,
+    ) -> Result<Option<Codec>> {
+        if compression == Compression::None {
+            return Ok(None);
+        }
+        let output = Self::run_command_on_client(
+            client,
+            config,
+            ssh_compression::DETECT_COMMAND.to_string(),
+            HELPER_COMMAND_OPTIONS,
+        )
+        .await?;
+        let codec = compression.choose(&output.stdout);
+        if codec.is_none() {
+            log::info!(
+                "No zstd or gzip on {}, transferring uncompressed",
+                config.host()
+            );
+        }
+        Ok(codec)
+    }
+
+    /// Returns `Ok(false)` when the server has no usable codec and the caller should upload
+    /// uncompressed.
+    async fn upload_local_file_compressed_on_client(
+        client: &mut SSHClient,
+        config: &SSHConfig,
+        local_path: &Path,
+        remote_path: &str,
+        options: &TransferOptions,
+        progress: &mut ProgressEmitter<'_>,
+        watchdog: &ssh_sftp::StallWatchdog,
+    ) -> Result<bool> {
+        let Some(codec) =
+            Self::negotiate_codec_on_client(client, config, options.compression).await?
+        else {
+            return Ok(false);
+        };
+        progress.compression = Some(codec);
+        let mut file = File::open(local_path).await?;
+        let total = file.metadata().await?.len();
+        let throttle = ssh_sftp::RateLimiter::for_options(options);
+        Self::upload_compressed_on_client(
+            client,
+            config,
+            &mut file,
+            total,
+            remote_path,
+            codec,
+            options,
+            throttle.as_deref(),
+            &mut |raw, wire, total| {
+                watchdog.record(raw, total);
+                progress.update_wire(raw, wire, total)
+            },
+        )
+        .await?;
+        Ok(true)
+    }
+
+    /// Compresses `source` locally and has the server decompress it into the partial file,
+    /// which is then verified and renamed like an SFTP upload. Reports `(raw, wire, total)`
+    /// bytes.
+    #[allow(clippy::too_many_arguments)]
+    async fn upload_compressed_on_client<R>(
+        client: &mut SSHClient,
+        config: &SSHConfig,
+        source: &mut R,
+        total: u64,
+        remote_path: &str,
+        codec: Codec,
+        options: &TransferOptions,
+        throttle: Option<&ssh_sftp::RateLimiter>,
+        on_progress: &mut impl FnMut(u64, u64, u64) -> Result<()>,
+    ) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let partial_shell_path = format!("{remote_path}{}", ssh_sftp::REMOTE_PARTIAL_SUFFIX);
+        let escaped_partial = shell_escape_remote_path(&partial_shell_path);
+        let mut channel = Self::open_channel_on_client(client, config).await?;
+        let command = format!("{} > {escaped_partial}", codec.decompress_command());
+        let transfer = TransferCommand::exec(&channel, config, command).await?;
+        let mut writer = channel.make_writer();
+
+        let mut encoder = codec.encoder()?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; 64 * 1024];
+        let mut raw = 0u64;
+        let mut wire = 0u64;
+        loop {
+            let n = source.read(&mut buffer).await?;
+            let compressed = match n {
+                0 => break,
+                n => encoder.compress(&buffer[..n])?,
+            };
+            hasher.update(&buffer[..n]);
+            if let Some(throttle) = throttle {
+                throttle.acquire(compressed.len()).await;
+            }
+            writer.write_all(&compressed).await?;
+            raw += n as u64;
+            wire += compressed.len() as u64;
+            on_progress(raw, wire, total)?;
+        }
+        let trailer = encoder.finish()?;
+        writer.write_all(&trailer).await?;
+        wire += trailer.len() as u64;
+        on_progress(raw, wire, total)?;
+
+        writer.shutdown().await?;
+        channel.eof().await?;
+        let exit_status = Self::wait_for_exit_status(&mut channel).await;
+        transfer.finish(exit_status, wire);
+        if exit_status != Some(0) {
+            anyhow::bail!(
+                "SSHDecompressFailed: {} exited with {exit_status:?} writing {remote_path}",
+                codec.name()
+            );
+        }
+        log::info!(
+            "Uploaded {remote_path} with {}: {raw} bytes as {wire} on the wire",
+            codec.name()
+        );
+
+        if options.verify_sha256 {
+            let local_hash = hex::encode(hasher.finalize());
+            let remote_hash =
+                Self::remote_sha256_on_client(client, config, &partial_shell_path).await?;
+            if remote_hash != local_hash {
+                let _ = Self::run_command_on_client(
+                    client,
+                    config,
+                    format!("rm -f {escaped_partial}"),
+                    HELPER_COMMAND_OPTIONS,
+                )
+                .await;
+                anyhow::bail!(
+                    "SSHChecksumMismatch: {remote_path} is {remote_hash} on the server, expected {local_hash}"
+                );
+            }
+        }
+        Self::commit_partial_on_client(client, config, remote_path, options).await
+    }
+
+    /// Has the server compress the file and decompresses it into a local partial file, which
+    /// is then verified and renamed into place. Reports `(raw, wire, total)` bytes.
+    async fn download_compressed_on_client(
+        client: &mut SSHClient,
+        config: &SSHConfig,
+        remote_path: &str,
+        local_path: &Path,
+        codec: Codec,
+        options: &TransferOptions,
+        on_progress: &mut impl FnMut(u64, u64, u64) -> Result<()>,
+    ) -> Result<()> {
+        let total = Self::remote_size_on_client(client, config, remote_path).await;
+        if let Some(parent) = local_path.parent() {
+            tokio::fs::create_dir_all(parent).await.ok();
+        }
+        let partial_path = PathBuf::from(format!(
+            "{}{}",
+            local_path.display(),
+            ssh_sftp::LOCAL_PARTIAL_SUFFIX
+        ));
+        let mut writer = BufWriter::new(File::create(&partial_path).await?);
+
+        let mut channel = Self::open_channel_on_client(client, config).await?;
+        let escaped_remote = shell_escape_remote_path(remote_path);
+        let command = format!("{} < {escaped_remote}", codec.compress_command());
+        let transfer = TransferCommand::exec(&channel, config, command).await?;
+
+        let mut decoder = codec.decoder()?;
+        let mut hasher = Sha256::new();
+        let mut raw = 0u64;
+        let mut wire = 0u64;
+        let mut exit_status = None;
+        while let Some(msg) = channel.wait().await {
+            match msg {
+                ChannelMsg::Data { ref data } => {
+                    let decoded = decoder.decompress(data)?;
+                    hasher.update(&decoded);
+                    writer.write_all(&decoded).await?;
+                    raw += decoded.len() as u64;
+                    wire += data.len() as u64;
+                    on_progress(raw, wire, total)?;
+                }
+                ChannelMsg::ExitStatus {
+                    exit_status: status,
+                } => exit_status = Some(status),
+                _ => {}
+            }
+        }
+        transfer.finish(exit_status, wire);
+        let decoded = decoder.finish()?;
+        hasher.update(&decoded);
+        writer.write_all(&decoded).await?;
+        writer.flush().await?;
+        drop(writer);
+        raw += decoded.len() as u64;
+        on_progress(raw, wire, total)?;
+
+        if exit_status != Some(0) {
+            let _ = tokio::fs::remove_file(&partial_path).await;
+            anyhow::bail!(
+                "SSHCompressFailed: {} exited with {exit_status:?} reading {remote_path}",
+                codec.name()
+            );
+        }
+        log::info!(
+            "Downloaded {remote_path} with {}: {raw} bytes as {wire} on the wire",
+            codec.name()
+        );
+
+        if options.verify_sha256 {
+            let local_hash = hex::encode(hasher.finalize());
+            let remote_hash = Self::remote_sha256_on_client(client, config, remote_path).await?;
+            if remote_hash != local_hash {
+                let _ = tokio::fs::remove_file(&partial_path).await;
+                anyhow::bail!(
+                    "SSHChecksumMismatch: downloaded {remote_path} is {local_hash}, expected {remote_hash}"
+                );
+            }
+        }
+        tokio::fs::rename(&partial_path, local_path).await?;
+        Ok(())
+    }
+
+    async fn wait_for_exit_status(channel: &mut Channel<Msg>) -> Option<u32> {
+        let mut exit_status = None;
+        while let Some(msg) = channel.wait().await {
+            if let ChannelMsg::ExitStatus {
+                exit_status: status,
+            } = msg
+            {
+                exit_status = Some(status);
+            }
+        }
+        exit_status
+    }
+
+    async fn upload_local_file_on_client(
+        client: &mut SSHClient,
+        config: &SSHConfig,
+        local_path: &Path,
+        remote_path: &str,
+        options: &TransferOptions,
+        throttle: Option<&ssh_sftp::RateLimiter>,
+        on_progress: &mut impl FnMut(u64, u64) -> Result<()>,
+    ) -> Result<()> {
+        let mut file = File::open(local_path).await?;
         let file_size = file.metadata().await?.len();
-        let mut reader = BufReader::new(file);
-        let mut buffer = [0u8; 64 * 1024];
-        let mut total = 0u64;
-        let mut last_percent = -1;
 
+        if Self::sftp_upload_on_client(
+            client,
+            config,
+            &mut file,
+            file_size,
+            remote_path,
+            options,
+            throttle,
+            on_progress,
+        )
+        .await?
+        {
+            return Ok(());
+        }
+
+        Self::cat_upload_on_client(
+            client,
+            config,
+            &mut BufReader::new(file),
+            file_size,
+            remote_path,
+            options,
+            throttle,
+            on_progress,
+        )
+        .await
+    }
+
+    /// Uploads without SFTP by piping into `cat` on the server. Like the SFTP path it writes
+    /// the partial file and only moves it into place once `cat` exited cleanly.
+    #[allow(clippy::too_many_arguments)]
+    async fn cat_upload_on_client<R>(
+        client: &mut SSHClient,
+        config: &SSHConfig,
+        source: &mut R,
+        total: u64,
+        remote_path: &str,
+        options: &TransferOptions,
+        throttle: Option<&ssh_sftp::RateLimiter>,
+        on_progress: &mut impl FnMut(u64, u64) -> Result<()>,
+    ) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let partial_shell_path = format!("{remote_path}{}", ssh_sftp::REMOTE_PARTIAL_SUFFIX);
+        let escaped_partial = shell_escape_remote_path(&partial_shell_path);
+        let mut channel = Self::open_channel_on_client(client, config).await?;
+        let command = format!("cat > {escaped_partial}");
+        let transfer = TransferCommand::exec(&channel, config, command).await?;
+        let mut writer = channel.make_writer();
+
+        let mut buffer = [0u8; 64 * 1024];
+        let mut transferred = 0u64;
         loop {
-            let n = reader.read(&mut buffer).await?;
+            let n = source.read(&mut buffer).await?;
             if n == 0 {
                 break;
             }
 
-            writer.write_all(&buffer[..n]).await?;
-            total += n as u64;
-
-            if file_size > 0 {
-                let percent = ((total.saturating_mul(100)) / file_size) as i32;
-                if percent != last_percent {
-                    last_percent = percent;
-                    trace!("Uploading {file_name}: {percent}%");
-                    app.emit(event_progress_key, percent)?;
-                }
+            if let Some(throttle) = throttle {
+                throttle.acquire(n).await;
             }
+            writer.write_all(&buffer[..n]).await?;
+            transferred += n as u64;
+            on_progress(transferred, total)?;
         }
 
         writer.shutdown().await?;
         channel.eof().await?;
-        while channel.wait().await.is_some() {}
-
-        if last_percent < 100 {
-            app.emit(event_progress_key, 100)?;
+        let exit_status = Self::wait_for_exit_status(&mut channel).await;
+        transfer.finish(exit_status, transferred);
+        if exit_status != Some(0) {
+            let _ = Self::run_command_on_client(
+                client,
+                config,
+                format!("rm -f {escaped_partial}"),
+                HELPER_COMMAND_OPTIONS,
+            )
+            .await;
+            anyhow::bail!("SSHUploadFailed: cat exited with {exit_status:?} writing {remote_path}");
         }
-        Ok(())
+        Self::commit_partial_on_client(client, config, remote_path, options).await
     }
 
     pub async fn download_remote_file(
@@ -374,28 +1634,30 @@ impl SSH {
         remote_path: &str,
         local_download_path: &str,
         event_progress_key: String,
+        options: TransferOptions,
     ) -> Result<()> {
         let app = app.clone();
         let remote_path = remote_path.to_string();
         let local_download_path = local_download_path.to_string();
-        let transfer_client = self.transfer_client.clone();
+        let transfer_sessions = self.transfer_sessions.clone();
         let config = self.config.clone();
-        let timeout_duration = Duration::from_secs(10);
+        // The worker thread doesn't inherit the task-local audit origin.
+        let origin = ssh_audit::current_origin();
         tauri::async_runtime::spawn_blocking(move || -> Result<()> {
-            tauri::async_runtime::block_on(async move {
-                let mut client = transfer_client.lock().await;
-                let client =
-                    Self::get_or_connect_client(&mut client, &config, timeout_duration).await?;
-                Self::download_remote_file_on_client(
-                    client,
+            tauri::async_runtime::block_on(ssh_audit::with_origin(origin, async move {
+                let mut session = transfer_sessions.checkout(&config).await?;
+                let result = Self::download_remote_file_on_client(
+                    session.client(),
                     &config,
                     &app,
                     &remote_path,
                     &local_download_path,
                     &event_progress_key,
+                    &options,
                 )
-                .await
-            })
+                .await;
+                session.finish(result)
+            }))
         })
         .await
         .map_err(|e| anyhow::anyhow!("SSH worker thread failed: {e}"))?
@@ -408,61 +1670,354 @@ impl SSH {
         remote_path: &str,
         local_download_path: &str,
         event_progress_key: &str,
+        options: &TransferOptions,
     ) -> Result<()> {
-        let escaped_remote = shell_escape_remote_path(remote_path);
+        let mut progress = ProgressEmitter::new(app, event_progress_key);
+        let local_path = Path::new(local_download_path);
+        match Self::negotiate_codec_on_client(client, config, options.compression).await? {
+            Some(codec) => {
+                progress.compression = Some(codec);
+                Self::download_compressed_on_client(
+                    client,
+                    config,
+                    remote_path,
+                    local_path,
+                    codec,
+                    options,
+                    &mut |raw, wire, total| progress.update_wire(raw, wire, total),
+                )
+                .await?
+            }
+            None => {
+                Self::download_to_local_file_on_client(
+                    client,
+                    config,
+                    remote_path,
+                    local_path,
+                    options,
+                    &mut |transferred, total| progress.update(transferred, total),
+                )
+                .await?
+            }
+        }
+        // If size was unknown, emit 100% at the end so the UI completes
+        progress.finish()
+    }
 
-        // Best-effort: get remote file size for progress (may fail; then size=0)
-        let mut remote_size: u64 = 0;
-        if let Ok((out, _code)) =
-            Self::run_command_on_client(client, config, format!("stat -c %s {escaped_remote}"))
-                .await
+    /// Downloads a remote file on a pooled transfer session, reporting `(transferred, total)` bytes.
+    pub async fn download_to_local_file(
+        &self,
+        remote_path: &str,
+        local_path: &Path,
+        options: &TransferOptions,
+        mut on_progress: impl FnMut(u64, u64) -> Result<()>,
+    ) -> Result<()> {
+        let mut session = self.transfer_sessions.checkout(&self.config).await?;
+        let result = Self::download_to_local_file_on_client(
+            session.client(),
+            &self.config,
+            remote_path,
+            local_path,
+            options,
+            &mut on_progress,
+        )
+        .await;
+        session.finish(result)
+    }
+
+    async fn download_to_local_file_on_client(
+        client: &mut SSHClient,
+        config: &SSHConfig,
+        remote_path: &str,
+        local_path: &Path,
+        options: &TransferOptions,
+        on_progress: &mut impl FnMut(u64, u64) -> Result<()>,
+    ) -> Result<()> {
+        if Self::sftp_download_on_client(
+            client,
+            config,
+            remote_path,
+            local_path,
+            options,
+            on_progress,
+        )
+        .await?
         {
-            if let Ok(sz) = out.trim().parse::<u64>() {
-                remote_size = sz;
-            }
+            return Ok(());
         }
 
-        // Ensure local directory exists and create/truncate the file
-        if let Some(parent) = std::path::Path::new(local_download_path).parent() {
+        let escaped_remote = shell_escape_remote_path(remote_path);
+
+        let remote_size = Self::remote_size_on_client(client, config, remote_path).await;
+
+        // Stream into a partial file so a failed read never clobbers the local target.
+        if let Some(parent) = local_path.parent() {
             tokio::fs::create_dir_all(parent).await.ok();
         }
-        let file = File::create(local_download_path).await?;
-        let mut writer = BufWriter::new(file);
+        let partial_path = PathBuf::from(format!(
+            "{}{}",
+            local_path.display(),
+            ssh_sftp::LOCAL_PARTIAL_SUFFIX
+        ));
+        let mut writer = BufWriter::new(File::create(&partial_path).await?);
 
         // Open a channel and stream the remote file via `cat`
         let mut channel = Self::open_channel_on_client(client, config).await?;
-        channel.exec(true, format!("cat {escaped_remote}")).await?;
-        {
-            let mut reader = channel.make_reader();
+        let command = format!("cat {escaped_remote}");
+        let transfer = TransferCommand::exec(&channel, config, command).await?;
+        channel.eof().await?;
+        let mut total = 0u64;
+        let mut exit_status = None;
+        while let Some(msg) = channel.wait().await {
+            match msg {
+                ChannelMsg::Data { ref data } => {
+                    writer.write_all(data).await?;
+                    total += data.len() as u64;
+                    on_progress(total, remote_size)?;
+                }
+                ChannelMsg::ExitStatus {
+                    exit_status: status,
+                } => exit_status = Some(status),
+                _ => {}
+            }
+        }
+        transfer.finish(exit_status, total);
+        writer.flush().await?;
+        drop(writer);
+
+        if exit_status != Some(0) {
+            let _ = tokio::fs::remove_file(&partial_path).await;
+            anyhow::bail!(
+                "SSHDownloadFailed: cat exited with {exit_status:?} reading {remote_path}"
+            );
+        }
+        tokio::fs::rename(&partial_path, local_path).await?;
+        Ok(())
+    }
+
+    /// Best-effort remote file size for progress; 0 when it cannot be read.
+    async fn remote_size_on_client(
+        client: &mut SSHClient,
+        config: &SSHConfig,
+        remote_path: &str,
+    ) -> u64 {
+        let escaped_remote = shell_escape_remote_path(remote_path);
+        Self::run_command_on_client(
+            client,
+            config,
+            // GNU stat, then BSD stat, then a full read as the portable last resort.
+            format!(
+                "stat -c %s {escaped_remote} 2>/dev/null || stat -f %z {escaped_remote} 2>/dev/null || wc -c < {escaped_remote}"
+            ),
+            HELPER_COMMAND_OPTIONS,
+        )
+        .await
+        .ok()
+        .and_then(|output| output.stdout.trim().parse().ok())
+        .unwrap_or(0)
+    }
 
-            // Stream copy with progress
-            let mut buf = [0u8; 64 * 1024]; // 64KB buffer
-            let mut total: u64 = 0;
-            let mut last_percent: i32 = -1;
+    /// Hex SHA-256 of a remote file, computed on the server.
+    pub async fn remote_sha256(&self, remote_path: &str) -> Result<String> {
+        let output = self
+            .run_command_with_options(sha256_command(remote_path), HELPER_COMMAND_OPTIONS)
+            .await?;
+        parse_remote_sha256(&output)
+    }
+
+    /// Uploads through SFTP into a partial file, verifies it and renames it into place.
+    /// Returns `Ok(false)` when SFTP cannot be used and the caller should fall back to `cat`.
+    #[allow(clippy::too_many_arguments)]
+    async fn sftp_upload_on_client<R>(
+        client: &mut SSHClient,
+        config: &SSHConfig,
+        source: &mut R,
+        total: u64,
+        remote_path: &str,
+        options: &TransferOptions,
+        throttle: Option<&ssh_sftp::RateLimiter>,
+        on_progress: &mut impl FnMut(u64, u64) -> Result<()>,
+    ) -> Result<bool>
+    where
+        R: AsyncRead + AsyncSeek + Unpin,
+    {
+        let Some(target) = ssh_sftp::remote_path(remote_path) else {
+            return Ok(false);
+        };
+        let channel = Self::open_channel_on_client(client, config).await?;
+        let Some(sftp) = ssh_sftp::open_session(channel).await? else {
+            log::info!("SFTP unavailable on {}, uploading with cat", config.host());
+            return Ok(false);
+        };
+
+        let partial = format!("{target}{}", ssh_sftp::REMOTE_PARTIAL_SUFFIX);
+        let partial_shell_path = format!("{remote_path}{}", ssh_sftp::REMOTE_PARTIAL_SUFFIX);
+        let result = async {
+            let mut offset = match options.resume {
+                true => ssh_sftp::remote_size(&sftp, &partial)
+                    .await
+                    .filter(|size| *size <= total)
+                    .unwrap_or(0),
+                false => 0,
+            };
             loop {
-                let n = reader.read(&mut buf).await?;
-                if n == 0 {
+                if offset > 0 {
+                    log::info!("Resuming upload of {remote_path} at {offset}/{total} bytes");
+                }
+                let local_hash = ssh_sftp::upload_from(
+                    &sftp,
+                    source,
+                    &partial,
+                    offset,
+                    total,
+                    throttle,
+                    on_progress,
+                )
+                .await?;
+                if !options.verify_sha256 {
                     break;
                 }
-                writer.write_all(&buf[..n]).await?;
-                total += n as u64;
-
-                if remote_size > 0 {
-                    let percent = ((total.saturating_mul(100)) / remote_size) as i32;
-                    if percent != last_percent {
-                        last_percent = percent;
-                        app.emit(event_progress_key, percent)?;
-                    }
+                let remote_hash =
+                    Self::remote_sha256_on_client(client, config, &partial_shell_path).await?;
+                if remote_hash == local_hash {
+                    break;
                 }
+
+                let _ = sftp.remove_file(&partial).await;
+                if offset == 0 {
+                    anyhow::bail!(
+                        "SSHChecksumMismatch: {remote_path} is {remote_hash} on the server, expected {local_hash}"
+                    );
+                }
+                // The resumed prefix did not match the source, so upload everything again.
+                offset = 0;
             }
-            writer.flush().await?;
+            ssh_sftp::apply_upload_attributes(&sftp, &partial, &target, options).await
         }
-        channel.eof().await?;
-        while channel.wait().await.is_some() {}
+        .await;
+        let _ = sftp.close().await;
+        result?;
 
-        // If size was unknown, emit 100% at the end so the UI completes
-        app.emit(event_progress_key, 100)?;
-        Ok(())
+        Self::move_partial_into_place_on_client(client, config, remote_path).await?;
+        Ok(true)
+    }
+
+    /// Downloads through SFTP into a local partial file, verifies it and renames it into place.
+    /// Returns `Ok(false)` when SFTP cannot be used and the caller should fall back to `cat`.
+    async fn sftp_download_on_client(
+        client: &mut SSHClient,
+        config: &SSHConfig,
+        remote_path: &str,
+        local_path: &Path,
+        options: &TransferOptions,
+        on_progress: &mut impl FnMut(u64, u64) -> Result<()>,
+    ) -> Result<bool> {
+        let Some(source) = ssh_sftp::remote_path(remote_path) else {
+            return Ok(false);
+        };
+        let channel = Self::open_channel_on_client(client, config).await?;
+        let Some(sftp) = ssh_sftp::open_session(channel).await? else {
+            log::info!(
+                "SFTP unavailable on {}, downloading with cat",
+                config.host()
+            );
+            return Ok(false);
+        };
+
+        let result = async {
+            let total = sftp.metadata(&source).await?.size.unwrap_or(0);
+            if let Some(parent) = local_path.parent() {
+                tokio::fs::create_dir_all(parent).await.ok();
+            }
+            let partial_path = PathBuf::from(format!(
+                "{}{}",
+                local_path.display(),
+                ssh_sftp::LOCAL_PARTIAL_SUFFIX
+            ));
+            let mut offset = match options.resume {
+                true => tokio::fs::metadata(&partial_path)
+                    .await
+                    .map(|metadata| metadata.len())
+                    .ok()
+                    .filter(|size| *size <= total)
+                    .unwrap_or(0),
+                false => 0,
+            };
+            loop {
+                if offset > 0 {
+                    log::info!("Resuming download of {remote_path} at {offset}/{total} bytes");
+                }
+                let mut file = tokio::fs::OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create(true)
+                    .truncate(offset == 0)
+                    .open(&partial_path)
+                    .await?;
+                let local_hash =
+                    ssh_sftp::download_into(&sftp, &source, &mut file, offset, total, on_progress)
+                        .await?;
+                drop(file);
+                if !options.verify_sha256 {
+                    break;
+                }
+                let remote_hash = Self::remote_sha256_on_client(client, config, remote_path).await?;
+                if remote_hash == local_hash {
+                    break;
+                }
+
+                let _ = tokio::fs::remove_file(&partial_path).await;
+                if offset == 0 {
+                    anyhow::bail!(
+                        "SSHChecksumMismatch: downloaded {remote_path} is {local_hash}, expected {remote_hash}"
+                    );
+                }
+                // The resumed prefix did not match the server's file, so download everything again.
+                offset = 0;
+            }
+            tokio::fs::rename(&partial_path, local_path).await?;
+            Ok(())
+        }
+        .await;
+        let _ = sftp.close().await;
+        result?;
+        Ok(true)
+    }
+
+    async fn remote_sha256_on_client(
+        client: &mut SSHClient,
+        config: &SSHConfig,
+        remote_path: &str,
+    ) -> Result<String> {
+        let output = Self::run_command_on_client(
+            client,
+            config,
+            sha256_command(remote_path),
+            HELPER_COMMAND_OPTIONS,
+        )
+        .await?;
+        parse_remote_sha256(&output)
+    }
+
+    pub async fn is_closed(&self) -> bool {
+        self.client.read().await.is_closed()
+    }
+
+    /// Sends a keepalive that requires a reply and returns the round trip time.
+    pub async fn ping(&self, timeout_duration: Duration) -> Result<Duration> {
+        let client = self.client.read().await;
+        let started = Instant::now();
+        timeout(timeout_duration, client.send_ping())
+            .await
+            .map_err(|_| anyhow::anyhow!("SSH keepalive timed out after {timeout_duration:?}"))??;
+        Ok(started.elapsed())
+    }
+
+    /// Replaces the shared session with a fresh one, keeping pooled transfer sessions as they are.
+    pub async fn reconnect(&self) -> Result<()> {
+        let mut client = self.client.write().await;
+        Self::disconnect_client(&mut client, &self.config.host()).await;
+        self.reconnect_shared_client(&mut client).await
     }
 
     async fn disconnect_client(client: &mut SSHClient, host: &str) {
@@ -490,15 +2045,12 @@ impl SSH {
     pub async fn close(&self) {
         let host = self.config.host();
 
-        if let Ok(mut handle) = self.client.try_lock() {
+        if let Ok(mut handle) = self.client.try_write() {
             Self::disconnect_client(&mut handle, &host).await;
         }
 
-        if let Ok(mut handle) = self.transfer_client.try_lock() {
-            if let Some(client) = handle.as_mut() {
-                Self::disconnect_client(client, &host).await;
-            }
-            *handle = None;
+        for mut client in self.transfer_sessions.close() {
+            Self::disconnect_client(&mut client, &host).await;
         }
     }
 
@@ -521,9 +2073,342 @@ impl SSH {
     }
 }
 
-fn shell_escape_remote_path(remote_path: &str) -> String {
-    let escape = |value: &str| value.replace('\'', "'\\''");
+/// Extra sessions dedicated to file transfers, so large copies do not share a session window
+/// with commands. Sessions are reused while healthy and discarded after any failure.
+struct TransferSessionPool {
+    idle: std::sync::Mutex<Vec<SSHClient>>,
+    permits: Arc<Semaphore>,
+    closed: AtomicBool,
+}
+
+impl TransferSessionPool {
+    fn new(size: usize) -> Arc<Self> {
+        Arc::new(Self {
+            idle: std::sync::Mutex::new(Vec::new()),
+            permits: Arc::new(Semaphore::new(size)),
+            closed: AtomicBool::new(false),
+        })
+    }
+
+    async fn checkout(self: &Arc<Self>, config: &SSHConfig) -> Result<PooledSession> {
+        let permit = self.permits.clone().acquire_owned().await?;
+        let idle = {
+            let mut idle = self.lock_idle();
+            std::iter::from_fn(|| idle.pop()).find(|client| !client.is_closed())
+        };
+        let client = match idle {
+            Some(client) => client,
+            None => SSH::connect_client(config, TRANSFER_CONNECT_TIMEOUT).await?,
+        };
+
+        Ok(PooledSession {
+            client: Some(client),
+            pool: self.clone(),
+            healthy: false,
+            _permit: permit,
+        })
+    }
+
+    fn close(&self) -> Vec<SSHClient> {
+        self.closed.store(true, Ordering::SeqCst);
+        std::mem::take(&mut *self.lock_idle())
+    }
+
+    fn lock_idle(&self) -> std::sync::MutexGuard<'_, Vec<SSHClient>> {
+        self.idle
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+struct PooledSession {
+    client: Option<SSHClient>,
+    pool: Arc<TransferSessionPool>,
+    healthy: bool,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl PooledSession {
+    fn client(&mut self) -> &mut SSHClient {
+        self.client
+            .as_mut()
+            .expect("pooled SSH session should exist until dropped")
+    }
+
+    /// Returns the session to the pool only if the transfer succeeded.
+    fn finish<T>(mut self, result: Result<T>) -> Result<T> {
+        self.healthy = result.is_ok();
+        result
+    }
+}
+
+impl Drop for PooledSession {
+    fn drop(&mut self) {
+        let Some(client) = self.client.take() else {
+            return;
+        };
+        if self.healthy && !client.is_closed() && !self.pool.closed.load(Ordering::SeqCst) {
+            self.pool.lock_idle().push(client);
+        }
+    }
+}
+
+/// Payload of transfer progress events.
+#[derive(Clone, Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TransferProgress {
+    percent: i32,
+    raw_bytes: u64,
+    /// Bytes sent over SSH, fewer than `raw_bytes` when compressed.
+    wire_bytes: u64,
+    total_bytes: u64,
+    /// Raw bytes per second since the transfer started.
+    bytes_per_sec: u64,
+    compression: Option<Codec>,
+}
+
+/// Emits whole-percent progress for a transfer, skipping repeats.
+struct ProgressEmitter<'a> {
+    app: &'a AppHandle,
+    event_key: &'a str,
+    last_percent: i32,
+    started_at: Instant,
+    raw_bytes: u64,
+    wire_bytes: u64,
+    total_bytes: u64,
+    compression: Option<Codec>,
+}
+
+impl<'a> ProgressEmitter<'a> {
+    fn new(app: &'a AppHandle, event_key: &'a str) -> Self {
+        Self {
+            app,
+            event_key,
+            last_percent: -1,
+            started_at: Instant::now(),
+            raw_bytes: 0,
+            wire_bytes: 0,
+            total_bytes: 0,
+            compression: None,
+        }
+    }
+
+    fn update(&mut self, transferred: u64, total: u64) -> Result<()> {
+        self.update_wire(transferred, transferred, total)
+    }
+
+    fn update_wire(&mut self, raw_bytes: u64, wire_bytes: u64, total: u64) -> Result<()> {
+        self.raw_bytes = raw_bytes;
+        self.wire_bytes = wire_bytes;
+        self.total_bytes = total;
+        if total == 0 {
+            return Ok(());
+        }
+        let percent = (raw_bytes.saturating_mul(100) / total) as i32;
+        if percent != self.last_percent {
+            self.last_percent = percent;
+            trace!("{}: {percent}%", self.event_key);
+            self.emit(percent)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if self.last_percent < 100 {
+            self.emit(100)?;
+        }
+        Ok(())
+    }
+
+    fn emit(&self, percent: i32) -> Result<()> {
+        let elapsed = self.started_at.elapsed().as_secs_f64();
+        let bytes_per_sec = match elapsed > 0.0 {
+            true => (self.raw_bytes as f64 / elapsed) as u64,
+            false => 0,
+        };
+        let progress = TransferProgress {
+            percent,
+            raw_bytes: self.raw_bytes,
+            wire_bytes: self.wire_bytes,
+            total_bytes: self.total_bytes,
+            bytes_per_sec,
+            compression: self.compression,
+        };
+        self.app.emit(self.event_key, progress)?;
+        Ok(())
+    }
+}
+
+fn signal_display_name(signal: &Sig) -> String {
+    match signal {
+        Sig::Custom(name) => name.clone(),
+        signal => format!("{signal:?}"),
+    }
+}
+
+/// Wraps a command so the shell reports its pid before exec'ing it, letting a cancel or timeout
+/// kill the process group it leads.
+fn pid_reporting_script(command: &str) -> String {
+    let script = format!(
+        "echo {COMMAND_PID_MARKER}$$; exec bash -c {}",
+        shell_quote(command)
+    );
+    format!("bash -c {}", shell_quote(&script))
+}
+
+fn kill_command(pid: u32) -> String {
+    format!("kill -TERM -- -{pid} 2>/dev/null || {{ pkill -TERM -P {pid}; kill -TERM {pid}; }}")
+}
+
+fn timed_out_pid<T>(result: &Result<T>) -> Option<u32> {
+    result
+        .as_ref()
+        .err()?
+        .downcast_ref::<CommandTimedOut>()?
+        .remote_pid
+}
+
+/// Strips the `COMMAND_PID_MARKER` line from the front of stdout and records the pid.
+#[derive(Default)]
+struct PidMarkerReader {
+    line: Vec<u8>,
+    done: bool,
+    pid: Option<u32>,
+}
+
+impl PidMarkerReader {
+    /// Returns the stdout that follows the marker line, or nothing while the line is incomplete.
+    fn feed(&mut self, data: &[u8]) -> Vec<u8> {
+        if self.done {
+            return data.to_vec();
+        }
+        self.line.extend_from_slice(data);
+        let Some(newline) = self.line.iter().position(|b| *b == b'\n') else {
+            return Vec::new();
+        };
+        self.done = true;
+        self.pid = std::str::from_utf8(&self.line[..newline])
+            .ok()
+            .and_then(|line| line.strip_prefix(COMMAND_PID_MARKER))
+            .and_then(|pid| pid.trim().parse::<u32>().ok());
+        let mut line = std::mem::take(&mut self.line);
+        if self.pid.is_some() {
+            line.split_off(newline + 1)
+        } else {
+            line
+        }
+    }
+
+    /// Returns a first line that never ended, which cannot have been the marker.
+    fn finish(&mut self) -> Vec<u8> {
+        self.done = true;
+        std::mem::take(&mut self.line)
+    }
+}
+
+struct LimitedOutput {
+    bytes: Vec<u8>,
+    limit: Option<usize>,
+    truncated: bool,
+}
+
+impl LimitedOutput {
+    fn new(limit: Option<usize>) -> Self {
+        Self {
+            bytes: Vec::new(),
+            limit,
+            truncated: false,
+        }
+    }
+
+    fn push(&mut self, data: &[u8]) {
+        let remaining = match self.limit {
+            Some(limit) => limit.saturating_sub(self.bytes.len()),
+            None => data.len(),
+        };
+        if data.len() > remaining {
+            self.truncated = true;
+        }
+        self.bytes
+            .extend_from_slice(&data[..data.len().min(remaining)]);
+    }
+
+    fn text(&self) -> String {
+        String::from_utf8_lossy(&self.bytes).into_owned()
+    }
+}
+
+fn sha256_command(remote_path: &str) -> String {
+    let escaped_remote = shell_escape_remote_path(remote_path);
+    format!("sha256sum -- {escaped_remote} 2>/dev/null || shasum -a 256 -- {escaped_remote}")
+}
+
+/// chmod, chown and `touch -t` for the attributes `options` requests, or `None` if there are none.
+/// `touch -t` takes a local time, so the shell runs it in UTC.
+fn attribute_command(remote_path: &str, options: &TransferOptions) -> Option<String> {
+    let escaped_remote = shell_escape_remote_path(remote_path);
+    let mut commands = Vec::new();
+    if let Some(mode) = options.mode {
+        commands.push(format!("chmod {:o} -- {escaped_remote}", mode & 0o7777));
+    }
+    match (options.uid, options.gid) {
+        (Some(uid), Some(gid)) => commands.push(format!("chown {uid}:{gid} -- {escaped_remote}")),
+        (Some(uid), None) => commands.push(format!("chown {uid} -- {escaped_remote}")),
+        (None, Some(gid)) => commands.push(format!("chgrp {gid} -- {escaped_remote}")),
+        (None, None) => {}
+    }
+    if let Some(mtime) = options.mtime {
+        let time = time::OffsetDateTime::from_unix_timestamp(i64::from(mtime)).ok()?;
+        commands.push(format!(
+            "TZ=UTC0 touch -t {:04}{:02}{:02}{:02}{:02}.{:02} -- {escaped_remote}",
+            time.year(),
+            u8::from(time.month()),
+            time.day(),
+            time.hour(),
+            time.minute(),
+            time.second()
+        ));
+    }
+    (!commands.is_empty()).then(|| commands.join(" && "))
+}
+
+fn parse_remote_sha256(output: &CommandOutput) -> Result<String> {
+    ssh_sftp::parse_sha256sum_output(&output.stdout)
+        .ok_or_else(|| anyhow::anyhow!("SSHRemoteChecksumUnavailable: {}", output.stderr.trim()))
+}
+
+/// Single-quotes `value` as one shell word.
+pub(crate) fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Decodes UTF-8 across chunk boundaries so multi-byte characters are never split.
+#[derive(Default)]
+pub(crate) struct Utf8ChunkDecoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8ChunkDecoder {
+    pub(crate) fn decode(&mut self, data: &[u8]) -> String {
+        self.pending.extend_from_slice(data);
+        let complete = match std::str::from_utf8(&self.pending) {
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            _ => self.pending.len(),
+        };
+        let incomplete = self.pending.split_off(complete);
+        let text = String::from_utf8_lossy(&self.pending).into_owned();
+        self.pending = incomplete;
+        text
+    }
+
+    pub(crate) fn finish(&mut self) -> String {
+        let text = String::from_utf8_lossy(&self.pending).into_owned();
+        self.pending.clear();
+        text
+    }
+}
 
+pub(crate) fn shell_escape_remote_path(remote_path: &str) -> String {
     if let Some(stripped) = remote_path.strip_prefix('~') {
         if stripped.is_empty() {
             return "~".to_string();
@@ -534,26 +2419,123 @@ fn shell_escape_remote_path(remote_path: &str) -> String {
             return if rest.is_empty() {
                 prefix.trim_end_matches('/').to_string()
             } else {
-                format!("{prefix}'{}'", escape(rest))
+                format!("{prefix}{}", shell_quote(rest))
             };
         }
     }
 
-    format!("'{}'", escape(remote_path))
+    shell_quote(remote_path)
+}
+
+/// A raw command that streams file data over its channel, like the `cat` and codec fallbacks.
+/// It is recorded in the audit log when it finishes, or as interrupted if dropped before that.
+struct TransferCommand<'a> {
+    config: &'a SSHConfig,
+    command: String,
+    started_at: Instant,
+    outcome: Option<(Option<u32>, u64)>,
+}
+
+impl<'a> TransferCommand<'a> {
+    async fn exec(channel: &Channel<Msg>, config: &'a SSHConfig, command: String) -> Result<Self> {
+        trace!("Executing ssh transfer command: {command}");
+        let transfer = Self {
+            config,
+            command,
+            started_at: Instant::now(),
+            outcome: None,
+        };
+        channel.exec(true, transfer.command.as_str()).await?;
+        Ok(transfer)
+    }
+
+    /// `bytes` is how much crossed the channel.
+    fn finish(mut self, exit_status: Option<u32>, bytes: u64) {
+        self.outcome = Some((exit_status, bytes));
+    }
+}
+
+impl Drop for TransferCommand<'_> {
+    fn drop(&mut self) {
+        let outcome = match self.outcome {
+            Some((Some(exit_status), bytes)) => Ok((exit_status, bytes as usize)),
+            Some((None, _)) => Err("SSHCommandMissingExitStatus"),
+            None => Err("SSHTransferInterrupted"),
+        };
+        self.config.audit(&self.command, self.started_at, outcome);
+    }
+}
+
+type Slot<T> = Arc<std::sync::Mutex<Option<T>>>;
+
+fn take_slot<T>(slot: &Slot<T>) -> Option<T> {
+    slot.lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .take()
+}
+
+fn fill_slot<T>(slot: &Slot<T>, value: T) {
+    *slot.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(value);
 }
 
-struct ClientHandler {}
+struct ClientHandler {
+    host: String,
+    // None only captures the presented key and rejects it (used for inspection).
+    known_hosts: Option<KnownHosts>,
+    presented_key: Slot<ssh_key::PublicKey>,
+    host_key_error: Slot<anyhow::Error>,
+    _jump_clients: Vec<SSHClient>,
+}
+
+impl ClientHandler {
+    fn new(host: String, known_hosts: Option<KnownHosts>) -> Self {
+        Self {
+            host,
+            known_hosts,
+            presented_key: Arc::new(std::sync::Mutex::new(None)),
+            host_key_error: Arc::new(std::sync::Mutex::new(None)),
+            _jump_clients: Vec::new(),
+        }
+    }
 
-// Explicitly implement Send for ClientHandler
-unsafe impl Send for ClientHandler {}
+    fn with_jump_clients(mut self, jump_clients: Vec<SSHClient>) -> Self {
+        self._jump_clients = jump_clients;
+        self
+    }
+}
 
 impl client::Handler for ClientHandler {
     type Error = russh::Error;
 
     async fn check_server_key(
         &mut self,
-        _server_public_key: &ssh_key::PublicKey,
+        server_public_key: &ssh_key::PublicKey,
     ) -> Result<bool, Self::Error> {
-        Ok(true)
+        fill_slot(&self.presented_key, server_public_key.clone());
+        let Some(known_hosts) = &self.known_hosts else {
+            return Ok(false);
+        };
+
+        match known_hosts.verify_or_pin(&self.host, server_public_key) {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                log::error!("Rejecting SSH host key for {}: {e:#}", self.host);
+                fill_slot(&self.host_key_error, e);
+                Ok(false)
+            }
+        }
+    }
+
+    async fn server_channel_open_forwarded_tcpip(
+        &mut self,
+        channel: Channel<Msg>,
+        _connected_address: &str,
+        connected_port: u32,
+        _originator_address: &str,
+        _originator_port: u32,
+        _session: &mut client::Session,
+    ) -> Result<(), Self::Error> {
+        ssh_tunnel::accept_forwarded(self.host.clone(), connected_port, channel);
+        Ok(())
     }
 }
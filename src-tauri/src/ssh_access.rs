@@ -1,29 +1,292 @@
 use crate::security::Security;
-use crate::ssh;
-use secrecy::{ExposeSecret, SecretString};
+use crate::ssh::{self, shell_quote};
+use crate::ssh_audit::AuditLog;
+use crate::ssh_ca::{self, CertificateAuthority, CertificatePermissions, CertificateRequest};
+use crate::ssh_identity::Identity;
+use crate::ssh_known_hosts::KnownHosts;
+use crate::ssh_operations;
+use crate::utils::Utils;
+use russh::keys::PublicKey;
 use sp_core::Pair;
+use std::collections::BTreeMap;
+use std::fs;
 use std::time::Duration;
-use tauri::{AppHandle, State};
+use tauri::{AppHandle, Manager, State};
 use tokio::sync::Mutex;
 
 const SSH_ACCESS_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_CERTIFICATE_VALIDITY: Duration = Duration::from_secs(60 * 60);
+// Backdate certificates slightly so a server clock running behind still accepts them.
+const CERTIFICATE_CLOCK_SKEW: Duration = Duration::from_secs(60);
+const ACCESS_SESSIONS_FILE_NAME: &str = "ssh_access_sessions.json";
+const ACCESS_KEY_COMMENT_PREFIX: &str = "argon-app-ssh:";
+// Read-only overview for support sessions; runs instead of whatever the client asks for.
+const DIAGNOSTICS_COMMAND: &str =
+    "uname -a; uptime; df -h; free -m 2>/dev/null; docker ps --all 2>/dev/null";
 
+type AccessSessions = BTreeMap<String, Vec<SshAccessSession>>;
+
+/// Temporary access sessions keyed by server address. Every change is written to the instance
+/// config dir, so keys handed out before a restart can still be listed and revoked.
+#[derive(Default)]
 pub struct SshAccessState {
-    pub access: Mutex<Option<SshAccessSession>>,
+    sessions: Mutex<AccessSessions>,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize)]
+struct AccessSessionsFile {
+    servers: AccessSessions,
 }
 
-#[derive(Clone)]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SshAccessSession {
-    private_key: SecretString,
+    id: String,
+    label: Option<String>,
+    host: String,
+    port: u16,
+    username: String,
+    created_at: i64,
+    /// Encrypted with `Security::encrypt_wallet_secret`.
+    encrypted_private_key: String,
     public_key: String,
+    expires_at: Option<i64>,
+    restrictions: AccessRestrictions,
+    certificate: Option<IssuedCertificate>,
+}
+
+impl SshAccessSession {
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+impl SshAccessState {
+    pub fn for_instance(app: &AppHandle) -> Self {
+        let sessions = load_sessions(app).unwrap_or_else(|e| {
+            log::error!("Could not load SSH access sessions: {e:#}");
+            AccessSessions::new()
+        });
+        Self {
+            sessions: Mutex::new(sessions),
+        }
+    }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IssuedCertificate {
+    openssh: String,
+    serial: u64,
+    principals: Vec<String>,
+    /// Whether the server has a KRL this app can add the serial to.
+    revocable: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AccessMode {
+    /// Append the key to `authorized_keys` and remove it again on deactivation.
+    #[default]
+    AuthorizedKey,
+    /// Issue a short-lived user certificate signed by the instance CA.
+    Certificate,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AccessPreset {
+    /// An unrestricted login shell.
+    #[default]
+    FullShell,
+    /// A shell without port, agent or X11 forwarding.
+    NoForwarding,
+    /// OpenSSH `restrict` with only a PTY allowed back.
+    RestrictedShell,
+    /// `restrict` plus a forced read-only diagnostics command.
+    Diagnostics,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AccessRestrictions {
+    pub preset: AccessPreset,
+    /// Source addresses or patterns for `from=`; empty allows any source.
+    pub from: Vec<String>,
+}
+
+impl AccessRestrictions {
+    /// The `authorized_keys` options in force for this policy.
+    pub fn options(&self) -> Result<Vec<String>, String> {
+        let mut options = match self.preset {
+            AccessPreset::FullShell => vec![],
+            AccessPreset::NoForwarding => vec![
+                "no-port-forwarding".to_string(),
+                "no-agent-forwarding".to_string(),
+                "no-X11-forwarding".to_string(),
+            ],
+            AccessPreset::RestrictedShell => vec!["restrict".to_string(), "pty".to_string()],
+            AccessPreset::Diagnostics => vec![
+                "restrict".to_string(),
+                format!("command=\"{DIAGNOSTICS_COMMAND}\""),
+            ],
+        };
+        if !self.from.is_empty() {
+            let invalid = |pattern: &String| {
+                pattern.is_empty()
+                    || pattern
+                        .chars()
+                        .any(|c| c == '"' || c == ',' || c == '\'' || c.is_whitespace())
+            };
+            if let Some(pattern) = self.from.iter().find(|pattern| invalid(pattern)) {
+                return Err(format!("Invalid source restriction: {pattern:?}"));
+            }
+            options.insert(0, format!("from=\"{}\"", self.from.join(",")));
+        }
+        Ok(options)
+    }
+
+    /// The same policy for certificates. `source-address` only takes addresses and CIDR blocks.
+    pub fn certificate_permissions(&self) -> Result<CertificatePermissions, String> {
+        const ALL_EXTENSIONS: [&str; 5] = [
+            "permit-X11-forwarding",
+            "permit-agent-forwarding",
+            "permit-port-forwarding",
+            "permit-pty",
+            "permit-user-rc",
+        ];
+        let (force_command, extensions) = match self.preset {
+            AccessPreset::FullShell => (None, ALL_EXTENSIONS.to_vec()),
+            AccessPreset::NoForwarding => (None, vec!["permit-pty", "permit-user-rc"]),
+            AccessPreset::RestrictedShell => (None, vec!["permit-pty"]),
+            AccessPreset::Diagnostics => (Some(DIAGNOSTICS_COMMAND.to_string()), vec![]),
+        };
+
+        let is_cidr = |pattern: &String| {
+            let (address, prefix) = match pattern.split_once('/') {
+                Some((address, prefix)) => (address, Some(prefix)),
+                None => (pattern.as_str(), None),
+            };
+            address.parse::<std::net::IpAddr>().is_ok()
+                && prefix.is_none_or(|prefix| prefix.parse::<u8>().is_ok())
+        };
+        if let Some(pattern) = self.from.iter().find(|pattern| !is_cidr(pattern)) {
+            return Err(format!(
+                "Certificate source restrictions must be addresses or CIDR blocks: {pattern:?}"
+            ));
+        }
+
+        Ok(CertificatePermissions {
+            force_command,
+            source_address: (!self.from.is_empty()).then(|| self.from.join(",")),
+            extensions,
+        })
+    }
 }
 
 #[derive(serde::Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SshAccessStatus {
     active: bool,
+    id: Option<String>,
+    address: Option<String>,
+    label: Option<String>,
+    created_at: Option<i64>,
     public_key: Option<String>,
     private_key: Option<String>,
+    expires_at: Option<i64>,
+    restrictions: Option<AccessRestrictions>,
+    /// The `authorized_keys` options the key was added with.
+    key_options: Vec<String>,
+    mode: Option<AccessMode>,
+    /// OpenSSH certificate to save next to the private key as `<key>-cert.pub`.
+    certificate: Option<String>,
+    certificate_serial: Option<u64>,
+    principals: Vec<String>,
+    /// Whether deactivation cuts access off before expiry.
+    revocable: bool,
+}
+
+impl SshAccessStatus {
+    fn inactive() -> Self {
+        Self {
+            active: false,
+            id: None,
+            address: None,
+            label: None,
+            created_at: None,
+            public_key: None,
+            private_key: None,
+            expires_at: None,
+            restrictions: None,
+            key_options: Vec::new(),
+            mode: None,
+            certificate: None,
+            certificate_serial: None,
+            principals: Vec::new(),
+            revocable: false,
+        }
+    }
+
+    fn for_session(
+        app: &AppHandle,
+        address: &str,
+        access: &SshAccessSession,
+    ) -> Result<Self, String> {
+        let private_key = Security::decrypt_wallet_secret(app, &access.encrypted_private_key)
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            // sshd stops accepting the key once it expires, even though it is still listed.
+            active: !access.is_expired(now_epoch()),
+            id: Some(access.id.clone()),
+            address: Some(address.to_string()),
+            label: access.label.clone(),
+            created_at: Some(access.created_at),
+            public_key: Some(access.public_key.clone()),
+            private_key: Some(private_key),
+            expires_at: access.expires_at,
+            key_options: match access.certificate {
+                Some(_) => Vec::new(),
+                None => access.restrictions.options().unwrap_or_default(),
+            },
+            restrictions: Some(access.restrictions.clone()),
+            mode: Some(match access.certificate {
+                Some(_) => AccessMode::Certificate,
+                None => AccessMode::AuthorizedKey,
+            }),
+            certificate: access.certificate.as_ref().map(|cert| cert.openssh.clone()),
+            certificate_serial: access.certificate.as_ref().map(|cert| cert.serial),
+            principals: access
+                .certificate
+                .as_ref()
+                .map(|cert| cert.principals.clone())
+                .unwrap_or_default(),
+            revocable: access
+                .certificate
+                .as_ref()
+                .is_none_or(|cert| cert.revocable),
+        })
+    }
+}
+
+/// An `argon-app-ssh:` key found in a server's `authorized_keys`.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessKeyEntry {
+    pub public_key: String,
+    pub created_at: Option<i64>,
+    pub expires_at: Option<i64>,
+    pub expired: bool,
+    /// Whether the key belongs to a session this app still tracks.
+    pub current: bool,
+}
+
+impl AccessKeyEntry {
+    /// Expired keys that no tracked session owns. Other keys, including ones without an expiry,
+    /// may belong to another install and are only stale when `include_unexpired` is set.
+    fn is_stale(&self, include_unexpired: bool) -> bool {
+        !self.current && (self.expired || include_unexpired)
+    }
 }
 
 fn now_epoch() -> i64 {
@@ -34,27 +297,40 @@ fn now_epoch() -> i64 {
         .as_secs() as i64
 }
 
+/// The newest tracked session, for `address` when given.
 #[tauri::command]
 pub async fn ssh_access_status(
+    app: AppHandle,
     state: State<'_, SshAccessState>,
+    address: Option<String>,
 ) -> Result<SshAccessStatus, String> {
-    let guard = state.access.lock().await;
-    if let Some(access) = guard.as_ref() {
-        Ok(SshAccessStatus {
-            active: true,
-            public_key: Some(access.public_key.clone()),
-            private_key: Some(access.private_key.expose_secret().to_string()),
-        })
-    } else {
-        Ok(SshAccessStatus {
-            active: false,
-            public_key: None,
-            private_key: None,
-        })
-    }
+    let sessions = state.sessions.lock().await;
+    newest_status(&app, &sessions, address.as_deref())
 }
 
 #[tauri::command]
+pub async fn ssh_access_list_sessions(
+    app: AppHandle,
+    state: State<'_, SshAccessState>,
+    address: Option<String>,
+) -> Result<Vec<SshAccessStatus>, String> {
+    let sessions = state.sessions.lock().await;
+    sessions
+        .iter()
+        .filter(|(server, _)| address.as_deref().is_none_or(|address| address == *server))
+        .flat_map(|(server, sessions)| sessions.iter().map(move |session| (server, session)))
+        .map(|(server, session)| SshAccessStatus::for_session(&app, server, session))
+        .collect()
+}
+
+/// Adds a fresh key to `authorized_keys`. With `expires_in_secs` the key carries an OpenSSH
+/// `expiry-time=` option, so sshd rejects it afterwards even if this app never cleans it up.
+/// `restrictions` picks a preset of key options and an optional `from=` source list.
+/// Each activation adds a session next to the address's existing ones, optionally `label`led.
+/// In certificate `mode` nothing is added per session: the server trusts the instance CA once,
+/// and the key comes with a certificate valid for `expires_in_secs` (one hour by default).
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub async fn ssh_access_activate(
     app: AppHandle,
     state: State<'_, SshAccessState>,
@@ -62,104 +338,770 @@ pub async fn ssh_access_activate(
     host: &str,
     port: u16,
     username: String,
+    expires_in_secs: Option<u64>,
+    restrictions: Option<AccessRestrictions>,
+    mode: Option<AccessMode>,
+    label: Option<String>,
 ) -> Result<SshAccessStatus, String> {
     log::debug!("Activating temporary SSH access for {address}");
-    if state.access.lock().await.is_some() {
-        clear_ssh_access(&app, &state, address, host, port, &username).await?;
-    }
-
+    ssh_operations::ensure_unrestricted_action_allowed("activating SSH access")?;
+    let restrictions = restrictions.unwrap_or_default();
+    let mode = mode.unwrap_or_default();
+    let key_options = restrictions.options()?;
+    let certificate_permissions = match mode {
+        AccessMode::AuthorizedKey => None,
+        AccessMode::Certificate => Some(restrictions.certificate_permissions()?),
+    };
     let ssh = open_access_connection(&app, host, port, &username).await?;
 
     let (pair, _phrase, _seed) = sp_core::ed25519::Pair::generate_with_phrase(None);
     let (private_key_openssh, public_key_openssh) =
         ssh::SSH::format_as_openssh(pair).map_err(|e| e.to_string())?;
     let now = now_epoch();
-    let comment = format!("argon-app-ssh:{now}");
-    let public_key_with_comment = format!("{} {}", public_key_openssh.trim(), comment);
-    let public_key_material = public_key_openssh.trim().to_string();
-
-    let add_cmd = format!(
-        "grep -q '{public_key_material}' ~/.ssh/authorized_keys || echo '{public_key_with_comment}' >> ~/.ssh/authorized_keys"
-    );
-    let access = SshAccessSession {
-        private_key: SecretString::new(private_key_openssh.clone().into()),
+    let encrypted_private_key =
+        Security::encrypt_wallet_secret(&app, &private_key_openssh).map_err(|e| e.to_string())?;
+    let session = |expires_at, certificate| SshAccessSession {
+        id: format!("{:016x}", rand::random::<u64>()),
+        label: label.clone(),
+        host: host.to_string(),
+        port,
+        username: username.clone(),
+        created_at: now,
+        encrypted_private_key: encrypted_private_key.clone(),
         public_key: public_key_openssh.clone(),
+        expires_at,
+        restrictions: restrictions.clone(),
+        certificate,
     };
+
+    if let Some(permissions) = certificate_permissions {
+        let result = issue_certificate(
+            &app,
+            &ssh,
+            &username,
+            &public_key_openssh,
+            now,
+            expires_in_secs,
+            &permissions,
+        )
+        .await;
+        ssh.close().await;
+        let (expires_at, certificate) = result?;
+        let access = session(Some(expires_at), Some(certificate));
+        add_session(&app, &state, address, access.clone()).await?;
+        return SshAccessStatus::for_session(&app, address, &access);
+    }
+
+    let expires_at = expires_in_secs.map(|secs| now.saturating_add(secs as i64));
+    let add_cmd = add_key_command(public_key_openssh.trim(), now, expires_at, &key_options);
+    let access = session(expires_at, None);
     let result = ssh.run_command(add_cmd).await;
     ssh.close().await;
 
-    // An SSH error can occur after the server added the key, so retain it for cleanup on retry.
-    *state.access.lock().await = Some(access);
-    result.map_err(|e| e.to_string())?;
+    // An SSH error can occur after the server added the key, so track it for later revocation.
+    add_session(&app, &state, address, access.clone()).await?;
+    let (output, code) = result.map_err(|e| e.to_string())?;
+    if code != 0 {
+        return Err(format!("Adding the access key failed: {}", output.trim()));
+    }
 
-    Ok(SshAccessStatus {
-        active: true,
-        public_key: Some(public_key_openssh),
-        private_key: Some(private_key_openssh),
-    })
+    SshAccessStatus::for_session(&app, address, &access)
 }
 
+/// Revokes the session `session_id`, or every session for `address` without one, and returns
+/// the newest session that is left.
 #[tauri::command]
 pub async fn ssh_access_deactivate(
     app: AppHandle,
     state: State<'_, SshAccessState>,
     address: &str,
+    session_id: Option<String>,
+) -> Result<SshAccessStatus, String> {
+    log::debug!("Deactivating temporary SSH access for {address}");
+    let sessions = state
+        .sessions
+        .lock()
+        .await
+        .get(address)
+        .into_iter()
+        .flatten()
+        .filter(|session| session_id.as_ref().is_none_or(|id| *id == session.id))
+        .cloned()
+        .collect::<Vec<_>>();
+    revoke_sessions(&app, &state, address, sessions).await?;
+
+    let sessions = state.sessions.lock().await;
+    newest_status(&app, &sessions, Some(address))
+}
+
+/// Lists every `argon-app-ssh:` key on the server, including ones left by crashed sessions.
+#[tauri::command]
+pub async fn ssh_access_list_keys(
+    app: AppHandle,
+    state: State<'_, SshAccessState>,
+    host: &str,
+    port: u16,
+    username: String,
+) -> Result<Vec<AccessKeyEntry>, String> {
+    let ssh = open_access_connection(&app, host, port, &username).await?;
+    let result = list_access_keys(&ssh, &state).await;
+    ssh.close().await;
+    result
+}
+
+/// Removes stale `argon-app-ssh:` keys and returns the ones it removed. Keys of tracked
+/// sessions are always kept.
+#[tauri::command]
+pub async fn ssh_access_revoke_stale(
+    app: AppHandle,
+    state: State<'_, SshAccessState>,
     host: &str,
     port: u16,
     username: String,
+    include_unexpired: Option<bool>,
+) -> Result<Vec<AccessKeyEntry>, String> {
+    let ssh = open_access_connection(&app, host, port, &username).await?;
+    let result = async {
+        let stale = list_access_keys(&ssh, &state)
+            .await?
+            .into_iter()
+            .filter(|entry| entry.is_stale(include_unexpired.unwrap_or(false)))
+            .collect::<Vec<_>>();
+        let keys = stale
+            .iter()
+            .map(|entry| entry.public_key.clone())
+            .collect::<Vec<_>>();
+        if !keys.is_empty() {
+            log::info!("Revoking {} stale SSH access keys on {host}", keys.len());
+            run_checked(&ssh, remove_keys_command(&keys)).await?;
+        }
+        Ok(stale)
+    }
+    .await;
+    ssh.close().await;
+    result
+}
+
+/// Revokes the sessions that expired while the app was closed and forgets them, then asks
+/// whether to revoke the sessions that never expire. Sessions that cannot be cleaned up stay
+/// registered and are retried on the next start.
+pub async fn cleanup_expired_sessions(app: AppHandle) {
+    let state = app.state::<SshAccessState>();
+    let now = now_epoch();
+    for (address, sessions) in sessions_matching(&state, |session| session.is_expired(now)).await {
+        log::info!(
+            "Cleaning up {} expired SSH access sessions for {address}",
+            sessions.len()
+        );
+        if let Err(e) = revoke_sessions(&app, &state, &address, sessions).await {
+            log::warn!("Could not clean up expired SSH access for {address}: {e}");
+        }
+    }
+
+    let unexpiring = sessions_matching(&state, |session| session.expires_at.is_none()).await;
+    if unexpiring.is_empty() || !confirm_revoke_unexpiring(&app, &unexpiring).await {
+        return;
+    }
+    for (address, sessions) in unexpiring {
+        log::info!(
+            "Revoking {} non-expiring SSH access sessions for {address}",
+            sessions.len()
+        );
+        if let Err(e) = revoke_sessions(&app, &state, &address, sessions).await {
+            log::warn!("Could not revoke SSH access for {address}: {e}");
+        }
+    }
+}
+
+async fn sessions_matching(
+    state: &SshAccessState,
+    matches: impl Fn(&SshAccessSession) -> bool,
+) -> Vec<(String, Vec<SshAccessSession>)> {
+    state
+        .sessions
+        .lock()
+        .await
+        .iter()
+        .map(|(address, sessions)| {
+            let matching = sessions
+                .iter()
+                .filter(|session| matches(session))
+                .cloned()
+                .collect::<Vec<_>>();
+            (address.clone(), matching)
+        })
+        .filter(|(_, matching)| !matching.is_empty())
+        .collect()
+}
+
+async fn confirm_revoke_unexpiring(
+    app: &AppHandle,
+    unexpiring: &[(String, Vec<SshAccessSession>)],
+) -> bool {
+    use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+    let servers = unexpiring
+        .iter()
+        .map(|(address, sessions)| format!("{address} ({} keys)", sessions.len()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .message(format!(
+            "Temporary SSH access that never expires is still installed on {servers}. \
+             Revoke it now?"
+        ))
+        .title("SSH access is still active")
+        .kind(MessageDialogKind::Warning)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            "Revoke".to_string(),
+            "Keep".to_string(),
+        ))
+        .show(move |revoke| {
+            let _ = tx.send(revoke);
+        });
+    rx.await.unwrap_or(false)
+}
+
+fn newest_status(
+    app: &AppHandle,
+    sessions: &AccessSessions,
+    address: Option<&str>,
 ) -> Result<SshAccessStatus, String> {
-    log::debug!("Deactivating temporary SSH access for {address}");
-    clear_ssh_access(&app, &state, address, host, port, &username).await?;
-    Ok(SshAccessStatus {
-        active: false,
-        public_key: None,
-        private_key: None,
-    })
+    let newest = sessions
+        .iter()
+        .filter(|(server, _)| address.is_none_or(|address| address == *server))
+        .flat_map(|(server, sessions)| sessions.iter().map(move |session| (server, session)))
+        .max_by_key(|(_, session)| session.created_at);
+    match newest {
+        Some((server, session)) => SshAccessStatus::for_session(app, server, session),
+        None => Ok(SshAccessStatus::inactive()),
+    }
+}
+
+async fn add_session(
+    app: &AppHandle,
+    state: &SshAccessState,
+    address: &str,
+    session: SshAccessSession,
+) -> Result<(), String> {
+    let mut sessions = state.sessions.lock().await;
+    sessions
+        .entry(address.to_string())
+        .or_default()
+        .push(session);
+    save_sessions(app, &sessions).map_err(|e| e.to_string())
 }
 
-async fn clear_ssh_access(
+/// Cleans up each login's sessions over one connection and forgets the ones that succeeded.
+async fn revoke_sessions(
     app: &AppHandle,
     state: &SshAccessState,
     address: &str,
+    sessions: Vec<SshAccessSession>,
+) -> Result<(), String> {
+    let mut by_login = BTreeMap::<_, Vec<_>>::new();
+    for session in sessions {
+        let login = (session.host.clone(), session.port, session.username.clone());
+        by_login.entry(login).or_default().push(session);
+    }
+
+    let mut revoked = Vec::new();
+    let mut first_error = None;
+    for ((host, port, username), sessions) in by_login {
+        match revoke_on_server(app, address, &host, port, &username, &sessions).await {
+            Ok(()) => revoked.extend(sessions.into_iter().map(|session| session.id)),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    let mut current = state.sessions.lock().await;
+    if let Some(sessions) = current.get_mut(address) {
+        sessions.retain(|session| !revoked.contains(&session.id));
+        if sessions.is_empty() {
+            current.remove(address);
+        }
+    }
+    save_sessions(app, &current).map_err(|e| e.to_string())?;
+    first_error.map_or(Ok(()), Err)
+}
+
+async fn revoke_on_server(
+    app: &AppHandle,
+    address: &str,
     host: &str,
     port: u16,
     username: &str,
+    sessions: &[SshAccessSession],
 ) -> Result<(), String> {
-    log::debug!("Clearing temporary SSH access for {address}");
-    let access = state.access.lock().await.clone();
-    if let Some(access) = access {
-        let ssh = open_access_connection(app, host, port, username).await?;
-
-        let remove_cmd = format!(
-            "if [ -f ~/.ssh/authorized_keys ]; then grep -v '{key}' ~/.ssh/authorized_keys > ~/.ssh/authorized_keys.tmp && mv ~/.ssh/authorized_keys.tmp ~/.ssh/authorized_keys; fi",
-            key = access.public_key.trim()
-        );
-        let result = ssh.run_command(remove_cmd).await;
-        ssh.close().await;
-        result.map_err(|e| e.to_string())?;
+    let now = now_epoch();
+    let mut commands = Vec::new();
+    let keys = sessions
+        .iter()
+        .filter(|session| session.certificate.is_none())
+        .map(|session| session.public_key.trim().to_string())
+        .collect::<Vec<_>>();
+    if !keys.is_empty() {
+        commands.push(remove_keys_command(&keys));
+    }
 
-        let mut current = state.access.lock().await;
-        // Do not clear a replacement session created while the remote cleanup was running.
-        if current
+    let mut serials = Vec::new();
+    for session in sessions {
+        // Expired certificates no longer authenticate, so there is nothing to revoke.
+        let Some(certificate) = session
+            .certificate
             .as_ref()
-            .is_some_and(|current| current.public_key == access.public_key)
-        {
-            current.take();
+            .filter(|_| !session.is_expired(now))
+        else {
+            continue;
+        };
+        if certificate.revocable {
+            serials.push(certificate.serial);
+        } else {
+            log::info!(
+                "Certificate {} for {address} cannot be revoked and stays valid until it expires",
+                certificate.serial
+            );
         }
     }
+    if !serials.is_empty() {
+        commands.push(ssh_ca::revoke_serials_command(&serials));
+    }
+    if commands.is_empty() {
+        return Ok(());
+    }
+
+    let ssh = open_access_connection(app, host, port, username).await?;
+    let mut result = Ok(());
+    for command in commands {
+        result = run_checked(&ssh, command).await.map(|_| ());
+        if result.is_err() {
+            break;
+        }
+    }
+    ssh.close().await;
+    result
+}
+
+fn load_sessions(app: &AppHandle) -> anyhow::Result<AccessSessions> {
+    let path = Utils::get_absolute_config_instance_dir(app).join(ACCESS_SESSIONS_FILE_NAME);
+    if !path.exists() {
+        return Ok(AccessSessions::new());
+    }
+    let file: AccessSessionsFile = serde_json::from_str(&fs::read_to_string(path)?)?;
+    Ok(file.servers)
+}
+
+fn save_sessions(app: &AppHandle, sessions: &AccessSessions) -> anyhow::Result<()> {
+    let path = Utils::get_absolute_config_instance_dir(app).join(ACCESS_SESSIONS_FILE_NAME);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = AccessSessionsFile {
+        servers: sessions.clone(),
+    };
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, serde_json::to_string_pretty(&file)?)?;
+    fs::rename(&tmp_path, &path)?;
     Ok(())
 }
 
+/// Whether the server trusts the instance CA for a user, and whether it can revoke certificates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaTrustStatus {
+    pub trusted: bool,
+    pub revocable: bool,
+}
+
+impl CaTrustStatus {
+    fn parse(output: &str) -> Self {
+        Self {
+            trusted: output.contains(ssh_ca::CA_TRUSTED_MARKER),
+            revocable: output.contains(ssh_ca::KRL_READY_MARKER),
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn ssh_access_ca_status(
+    app: AppHandle,
+    host: &str,
+    port: u16,
+    username: String,
+) -> Result<CaTrustStatus, String> {
+    let ca = CertificateAuthority::for_instance(&app).map_err(|e| e.to_string())?;
+    let command = ca.trust_status_command().map_err(|e| e.to_string())?;
+    run_ca_command(&app, host, port, &username, command).await
+}
+
+/// Adds a standing `cert-authority` trust for the instance CA to the user's `authorized_keys`
+/// (plus a KRL as root), which certificate sessions need. It outlives every session, so the UI
+/// confirms it on its own rather than as part of activating access.
+#[tauri::command]
+pub async fn ssh_access_install_ca(
+    app: AppHandle,
+    host: &str,
+    port: u16,
+    username: String,
+) -> Result<CaTrustStatus, String> {
+    log::info!("Installing the SSH access CA for {username}@{host}:{port}");
+    ssh_operations::ensure_unrestricted_action_allowed("installing the SSH access CA")?;
+    let ca = CertificateAuthority::for_instance(&app).map_err(|e| e.to_string())?;
+    let command = ca.install_trust_command().map_err(|e| e.to_string())?;
+    run_ca_command(&app, host, port, &username, command).await
+}
+
+/// Removes the CA trust again. Certificates issued under it stop working right away.
+#[tauri::command]
+pub async fn ssh_access_uninstall_ca(
+    app: AppHandle,
+    host: &str,
+    port: u16,
+    username: String,
+) -> Result<CaTrustStatus, String> {
+    log::info!("Removing the SSH access CA for {username}@{host}:{port}");
+    ssh_operations::ensure_unrestricted_action_allowed("removing the SSH access CA")?;
+    let ca = CertificateAuthority::for_instance(&app).map_err(|e| e.to_string())?;
+    let command = format!(
+        "{} && {}",
+        ca.uninstall_trust_command().map_err(|e| e.to_string())?,
+        ca.trust_status_command().map_err(|e| e.to_string())?
+    );
+    run_ca_command(&app, host, port, &username, command).await
+}
+
+async fn run_ca_command(
+    app: &AppHandle,
+    host: &str,
+    port: u16,
+    username: &str,
+    command: String,
+) -> Result<CaTrustStatus, String> {
+    let ssh = open_access_connection(app, host, port, username).await?;
+    let result = run_checked(&ssh, command).await;
+    ssh.close().await;
+    Ok(CaTrustStatus::parse(&result?))
+}
+
+/// Signs a certificate for `public_key`, once the server trusts the instance CA.
+async fn issue_certificate(
+    app: &AppHandle,
+    ssh: &ssh::SSH,
+    username: &str,
+    public_key: &str,
+    now: i64,
+    expires_in_secs: Option<u64>,
+    permissions: &CertificatePermissions,
+) -> Result<(i64, IssuedCertificate), String> {
+    let ca = CertificateAuthority::for_instance(app).map_err(|e| e.to_string())?;
+    let trust = CaTrustStatus::parse(
+        &run_checked(ssh, ca.trust_status_command().map_err(|e| e.to_string())?).await?,
+    );
+    if !trust.trusted {
+        return Err(format!(
+            "SSHCertificateAuthorityNotTrusted: install the SSH access CA for {username} first"
+        ));
+    }
+
+    let validity = expires_in_secs.unwrap_or(DEFAULT_CERTIFICATE_VALIDITY.as_secs());
+    let expires_at = now.saturating_add(validity as i64);
+    let principals = vec![username.to_string()];
+    let serial = rand::random::<u64>();
+    let subject = PublicKey::from_openssh(public_key.trim()).map_err(|e| e.to_string())?;
+    let certificate = ca
+        .issue(
+            &subject,
+            &CertificateRequest {
+                key_id: format!("{ACCESS_KEY_COMMENT_PREFIX}{now}"),
+                serial,
+                principals: &principals,
+                valid_after: (now as u64).saturating_sub(CERTIFICATE_CLOCK_SKEW.as_secs()),
+                valid_before: expires_at as u64,
+                permissions,
+            },
+        )
+        .and_then(|certificate| Ok(certificate.to_openssh()?))
+        .map_err(|e| e.to_string())?;
+
+    Ok((
+        expires_at,
+        IssuedCertificate {
+            openssh: certificate,
+            serial,
+            principals,
+            revocable: trust.revocable,
+        },
+    ))
+}
+
 async fn open_access_connection(
     app: &AppHandle,
     host: &str,
     port: u16,
     username: &str,
 ) -> Result<ssh::SSH, String> {
-    let private_key = Security::expose_private_key_openssh(app).map_err(|e| e.to_string())?;
-    let config = ssh::SSHConfig::new(host, port, username.to_string(), private_key)
-        .map_err(|e| e.to_string())?;
+    let identity = Identity::mnemonic(app).map_err(|e| e.to_string())?;
+    let config = ssh::SSHConfig::new(
+        host,
+        port,
+        username.to_string(),
+        identity,
+        KnownHosts::for_instance(app),
+    )
+    .with_audit_log(AuditLog::for_instance(app));
     ssh::SSH::connect(&config, SSH_ACCESS_CONNECT_TIMEOUT)
         .await
         .map_err(|e| e.to_string())
 }
+
+async fn list_access_keys(
+    ssh: &ssh::SSH,
+    state: &SshAccessState,
+) -> Result<Vec<AccessKeyEntry>, String> {
+    let authorized_keys =
+        run_checked(ssh, "cat ~/.ssh/authorized_keys 2>/dev/null || true").await?;
+    let tracked_keys = state
+        .sessions
+        .lock()
+        .await
+        .values()
+        .flatten()
+        .map(|session| session.public_key.trim().to_string())
+        .collect::<Vec<_>>();
+    Ok(parse_access_keys(
+        &authorized_keys,
+        now_epoch(),
+        &tracked_keys,
+    ))
+}
+
+async fn run_checked(ssh: &ssh::SSH, command: impl std::fmt::Display) -> Result<String, String> {
+    let (output, code) = ssh.run_command(command).await.map_err(|e| e.to_string())?;
+    if code != 0 {
+        return Err(format!("Remote command failed ({code}): {}", output.trim()));
+    }
+    Ok(output)
+}
+
+/// The expiry is rendered by the server's `date`, because sshd reads `expiry-time` in its own
+/// local time zone. The unix timestamps are kept in the comment for listing.
+fn add_key_command(
+    public_key: &str,
+    created_at: i64,
+    expires_at: Option<i64>,
+    key_options: &[String],
+) -> String {
+    let comment = match expires_at {
+        Some(expires_at) => format!("{ACCESS_KEY_COMMENT_PREFIX}{created_at}:{expires_at}"),
+        None => format!("{ACCESS_KEY_COMMENT_PREFIX}{created_at}"),
+    };
+    let options = key_options.join(",");
+    let skip_if_present =
+        format!("grep -q -F '{public_key}' ~/.ssh/authorized_keys 2>/dev/null ||");
+    let Some(expires_at) = expires_at else {
+        let line = match options.as_str() {
+            "" => format!("{public_key} {comment}"),
+            options => format!("{options} {public_key} {comment}"),
+        };
+        return format!(
+            "{skip_if_present} printf '%s\\n' {} >> ~/.ssh/authorized_keys",
+            shell_quote(&line)
+        );
+    };
+    let rest = match options.as_str() {
+        "" => format!(" {public_key} {comment}"),
+        options => format!(",{options} {public_key} {comment}"),
+    };
+    format!(
+        "expiry=$(date -d @{expires_at} +%Y%m%d%H%M%S 2>/dev/null || date -r {expires_at} +%Y%m%d%H%M%S) && \
+         {{ {skip_if_present} printf 'expiry-time=\"%s\"%s\\n' \"$expiry\" {} >> ~/.ssh/authorized_keys; }}",
+        shell_quote(&rest)
+    )
+}
+
+/// `grep` exits with 1 when every line was removed, which still is a successful rewrite.
+fn remove_keys_command(public_keys: &[String]) -> String {
+    let patterns = public_keys
+        .iter()
+        .map(|key| format!("-e '{key}'"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "if [ -f ~/.ssh/authorized_keys ]; then grep -v -F {patterns} ~/.ssh/authorized_keys > ~/.ssh/authorized_keys.tmp; [ $? -le 1 ] && mv ~/.ssh/authorized_keys.tmp ~/.ssh/authorized_keys; fi"
+    )
+}
+
+fn parse_access_keys(
+    authorized_keys: &str,
+    now: i64,
+    tracked_keys: &[String],
+) -> Vec<AccessKeyEntry> {
+    authorized_keys
+        .lines()
+        .filter_map(|line| {
+            let comment_start = line.find(ACCESS_KEY_COMMENT_PREFIX)?;
+            let stamps = line[comment_start + ACCESS_KEY_COMMENT_PREFIX.len()..].trim();
+            let mut stamps = stamps.split(':').map(|stamp| stamp.parse::<i64>().ok());
+            let created_at = stamps.next().flatten();
+            let expires_at = stamps.next().flatten();
+
+            // The key is the `type base64` pair right before the comment; options may precede it.
+            let mut key_fields = line[..comment_start].split_whitespace().rev();
+            let key_data = key_fields.next()?;
+            let key_type = key_fields.next()?;
+            let public_key = format!("{key_type} {key_data}");
+
+            Some(AccessKeyEntry {
+                current: tracked_keys.contains(&public_key),
+                expired: expires_at.is_some_and(|expires_at| expires_at <= now),
+                public_key,
+                created_at,
+                expires_at,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_restriction_presets_as_key_options() {
+        assert!(
+            AccessRestrictions::default()
+                .options()
+                .expect("default should render")
+                .is_empty()
+        );
+
+        let diagnostics = AccessRestrictions {
+            preset: AccessPreset::Diagnostics,
+            from: vec!["203.0.113.7".to_string(), "10.0.0.0/8".to_string()],
+        };
+        let options = diagnostics.options().expect("diagnostics should render");
+        assert_eq!(options[0], "from=\"203.0.113.7,10.0.0.0/8\"");
+        assert_eq!(options[1], "restrict");
+        assert!(options[2].starts_with("command=\"uname -a;"));
+
+        let command = add_key_command("ssh-ed25519 AAAAkey", 1000, Some(2000), &options);
+        assert!(command.contains("printf 'expiry-time=\"%s\"%s\\n' \"$expiry\" ',from="));
+        assert!(command.ends_with(
+            " ssh-ed25519 AAAAkey argon-app-ssh:1000:2000' >> ~/.ssh/authorized_keys; }"
+        ));
+
+        let injected = AccessRestrictions {
+            preset: AccessPreset::FullShell,
+            from: vec!["1.2.3.4\" command=\"sh".to_string()],
+        };
+        assert!(injected.options().is_err());
+    }
+
+    #[test]
+    fn maps_restriction_presets_to_certificate_permissions() {
+        let permissions = AccessRestrictions {
+            preset: AccessPreset::RestrictedShell,
+            from: vec!["203.0.113.7".to_string(), "10.0.0.0/8".to_string()],
+        }
+        .certificate_permissions()
+        .expect("restrictions should map");
+        assert_eq!(permissions.extensions, ["permit-pty"]);
+        assert_eq!(
+            permissions.source_address.as_deref(),
+            Some("203.0.113.7,10.0.0.0/8")
+        );
+        assert_eq!(permissions.force_command, None);
+
+        let hostname = AccessRestrictions {
+            preset: AccessPreset::FullShell,
+            from: vec!["*.example.com".to_string()],
+        };
+        assert!(hostname.options().is_ok());
+        assert!(hostname.certificate_permissions().is_err());
+    }
+
+    #[test]
+    fn round_trips_persisted_sessions_by_address() {
+        let session = |id: &str, expires_at| SshAccessSession {
+            id: id.to_string(),
+            label: Some("support".to_string()),
+            host: "203.0.113.7".to_string(),
+            port: 22,
+            username: "argon".to_string(),
+            created_at: 1000,
+            encrypted_private_key: "ciphertext".to_string(),
+            public_key: format!("ssh-ed25519 AAAA{id}"),
+            expires_at,
+            restrictions: AccessRestrictions::default(),
+            certificate: None,
+        };
+        let file = AccessSessionsFile {
+            servers: BTreeMap::from([(
+                "203.0.113.7:22".to_string(),
+                vec![session("a", Some(2000)), session("b", None)],
+            )]),
+        };
+
+        let json = serde_json::to_string(&file).expect("sessions should serialize");
+        assert!(json.contains("\"encryptedPrivateKey\":\"ciphertext\""));
+        let restored: AccessSessionsFile =
+            serde_json::from_str(&json).expect("sessions should deserialize");
+        let sessions = &restored.servers["203.0.113.7:22"];
+        assert_eq!(sessions.len(), 2);
+        assert!(sessions[0].is_expired(5000));
+        assert!(!sessions[1].is_expired(5000));
+    }
+
+    #[test]
+    fn parses_access_keys_with_and_without_expiry() {
+        let authorized_keys = "\
+ssh-ed25519 AAAAowner owner@laptop
+ssh-ed25519 AAAAlegacy argon-app-ssh:1000
+expiry-time=\"202610191200\" ssh-ed25519 AAAAexpired argon-app-ssh:1000:2000
+expiry-time=\"203001010000\" ssh-ed25519 AAAAcurrent argon-app-ssh:1500:9000
+expiry-time=\"203001010000\" ssh-ed25519 AAAAother argon-app-ssh:1500:9000
+";
+        let entries = parse_access_keys(
+            authorized_keys,
+            5000,
+            &["ssh-ed25519 AAAAcurrent".to_string()],
+        );
+
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| entry.public_key.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "ssh-ed25519 AAAAlegacy",
+                "ssh-ed25519 AAAAexpired",
+                "ssh-ed25519 AAAAcurrent",
+                "ssh-ed25519 AAAAother",
+            ]
+        );
+        assert_eq!(entries[0].created_at, Some(1000));
+        assert_eq!(entries[0].expires_at, None);
+        assert!(entries[1].expired);
+        assert!(entries[2].current && !entries[2].expired);
+
+        let stale = |include_unexpired| {
+            entries
+                .iter()
+                .filter(|entry| entry.is_stale(include_unexpired))
+                .map(|entry| entry.public_key.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(stale(false), ["ssh-ed25519 AAAAexpired"]);
+        assert_eq!(
+            stale(true),
+            [
+                "ssh-ed25519 AAAAlegacy",
+                "ssh-ed25519 AAAAexpired",
+                "ssh-ed25519 AAAAother"
+            ]
+        );
+    }
+}
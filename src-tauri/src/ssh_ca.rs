@@ -0,0 +1,231 @@
+use crate::security::Security;
+use anyhow::Result;
+use russh::keys::ssh_key::certificate::{Builder, CertType};
+use russh::keys::ssh_key::rand_core::OsRng;
+use russh::keys::{Certificate, PrivateKey, PublicKey, decode_secret_key};
+use secrecy::ExposeSecret;
+use tauri::AppHandle;
+
+pub const CA_TRUST_COMMENT: &str = "argon-app-ssh-ca";
+const SYSTEM_CA_PUBLIC_KEY_PATH: &str = "/etc/ssh/argon_user_ca.pub";
+const SYSTEM_KRL_PATH: &str = "/etc/ssh/argon_revoked_keys.krl";
+const SYSTEM_SSHD_DROP_IN_PATH: &str = "/etc/ssh/sshd_config.d/argon-app-ssh-ca.conf";
+pub const KRL_READY_MARKER: &str = "__ARGON_KRL_READY__";
+pub const CA_TRUSTED_MARKER: &str = "__ARGON_CA_TRUSTED__";
+
+/// What a certificate allows, expressed as OpenSSH critical options and extensions.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CertificatePermissions {
+    pub force_command: Option<String>,
+    /// Comma-separated CIDR list for the `source-address` critical option.
+    pub source_address: Option<String>,
+    pub extensions: Vec<&'static str>,
+}
+
+pub struct CertificateRequest<'a> {
+    pub key_id: String,
+    pub serial: u64,
+    pub principals: &'a [String],
+    pub valid_after: u64,
+    pub valid_before: u64,
+    pub permissions: &'a CertificatePermissions,
+}
+
+/// Signs short-lived user certificates with a key derived from the wallet mnemonic.
+pub struct CertificateAuthority {
+    key: PrivateKey,
+}
+
+impl CertificateAuthority {
+    pub fn for_instance(app: &AppHandle) -> Result<Self> {
+        let private_key = Security::expose_ssh_ca_private_key_openssh(app)?;
+        Ok(Self::new(decode_secret_key(
+            private_key.expose_secret(),
+            None,
+        )?))
+    }
+
+    pub fn new(key: PrivateKey) -> Self {
+        Self { key }
+    }
+
+    pub fn public_key_openssh(&self) -> Result<String> {
+        let mut public_key = PublicKey::from(&self.key);
+        public_key.set_comment("");
+        Ok(public_key.to_openssh()?)
+    }
+
+    pub fn issue(&self, subject: &PublicKey, request: &CertificateRequest) -> Result<Certificate> {
+        let mut builder = Builder::new_with_random_nonce(
+            &mut OsRng,
+            subject.key_data().clone(),
+            request.valid_after,
+            request.valid_before,
+        )?;
+        builder
+            .serial(request.serial)?
+            .cert_type(CertType::User)?
+            .key_id(request.key_id.clone())?;
+        for principal in request.principals {
+            builder.valid_principal(principal.clone())?;
+        }
+        if let Some(command) = &request.permissions.force_command {
+            builder.critical_option("force-command", command.clone())?;
+        }
+        if let Some(source_address) = &request.permissions.source_address {
+            builder.critical_option("source-address", source_address.clone())?;
+        }
+        for extension in &request.permissions.extensions {
+            builder.extension(*extension, "")?;
+        }
+        Ok(builder.sign(&self.key)?)
+    }
+
+    /// Trusts the CA for the connecting user. As root it also sets up a system-wide KRL so that
+    /// certificates can be revoked before they expire; the KRL exists before sshd is told to read
+    /// it, because a missing `RevokedKeys` file makes sshd refuse every key. A `RevokedKeys` the
+    /// server already has is left alone, and certificates then stay valid until they expire.
+    pub fn install_trust_command(&self) -> Result<String> {
+        let ca_public_key = self.public_key_openssh()?;
+        let trust_line = format!("cert-authority {ca_public_key} {CA_TRUST_COMMENT}");
+        Ok(format!(
+            "mkdir -p ~/.ssh && chmod 700 ~/.ssh && \
+             {{ grep -q -F '{ca_public_key}' ~/.ssh/authorized_keys 2>/dev/null || printf '%s\\n' '{trust_line}' >> ~/.ssh/authorized_keys; }} && \
+             if [ \"$(id -u)\" = 0 ] && [ -d /etc/ssh/sshd_config.d ] && [ ! -f {SYSTEM_SSHD_DROP_IN_PATH} ] && command -v ssh-keygen >/dev/null \
+                && ! grep -rqsiE '^[[:space:]]*RevokedKeys[[:space:]]' /etc/ssh/sshd_config /etc/ssh/sshd_config.d; then \
+               printf '%s\\n' '{ca_public_key}' > {SYSTEM_CA_PUBLIC_KEY_PATH} && \
+               ssh-keygen -k -f {SYSTEM_KRL_PATH} /dev/null >/dev/null 2>&1 && \
+               printf 'RevokedKeys %s\\n' {SYSTEM_KRL_PATH} > {SYSTEM_SSHD_DROP_IN_PATH} && \
+               {{ sshd -t && {{ systemctl reload ssh 2>/dev/null || systemctl reload sshd 2>/dev/null || true; }} || rm -f {SYSTEM_SSHD_DROP_IN_PATH}; }}; \
+             fi; {}",
+            self.trust_status_command()?
+        ))
+    }
+
+    /// Prints `CA_TRUSTED_MARKER` when the connecting user trusts this CA and `KRL_READY_MARKER`
+    /// when its certificates can be revoked. Changes nothing.
+    pub fn trust_status_command(&self) -> Result<String> {
+        let ca_public_key = self.public_key_openssh()?;
+        Ok(format!(
+            "if grep -q -F '{ca_public_key}' ~/.ssh/authorized_keys 2>/dev/null; then echo {CA_TRUSTED_MARKER}; fi; \
+             if [ -f {SYSTEM_SSHD_DROP_IN_PATH} ] && [ -w {SYSTEM_KRL_PATH} ]; then echo {KRL_READY_MARKER}; fi"
+        ))
+    }
+
+    /// Undoes `install_trust_command`: removes the `cert-authority` line and, as root, the KRL
+    /// drop-in before the files it points at.
+    pub fn uninstall_trust_command(&self) -> Result<String> {
+        let ca_public_key = self.public_key_openssh()?;
+        Ok(format!(
+            "if [ -f ~/.ssh/authorized_keys ]; then \
+               tmp=$(mktemp) && {{ grep -v -F '{ca_public_key}' ~/.ssh/authorized_keys > \"$tmp\"; [ $? -le 1 ]; }} && \
+               cat \"$tmp\" > ~/.ssh/authorized_keys; status=$?; rm -f \"$tmp\"; [ $status = 0 ] || exit $status; \
+             fi; \
+             if [ \"$(id -u)\" = 0 ] && [ -f {SYSTEM_SSHD_DROP_IN_PATH} ]; then \
+               rm -f {SYSTEM_SSHD_DROP_IN_PATH} && \
+               {{ systemctl reload ssh 2>/dev/null || systemctl reload sshd 2>/dev/null || true; }} && \
+               rm -f {SYSTEM_KRL_PATH} {SYSTEM_CA_PUBLIC_KEY_PATH}; \
+             fi"
+        ))
+    }
+}
+
+/// Adds certificate serials to the system KRL; sshd rereads it on every authentication.
+pub fn revoke_serials_command(serials: &[u64]) -> String {
+    let spec = serials
+        .iter()
+        .map(|serial| format!("serial: {serial}"))
+        .collect::<Vec<_>>()
+        .join("\\n");
+    format!(
+        "spec=$(mktemp) && printf '{spec}\\n' > \"$spec\" && \
+         ssh-keygen -k -u -s {SYSTEM_CA_PUBLIC_KEY_PATH} -f {SYSTEM_KRL_PATH} \"$spec\"; \
+         status=$?; rm -f \"$spec\"; exit $status"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use russh::keys::HashAlg;
+    use russh::keys::ssh_key::private::{Ed25519Keypair, Ed25519PrivateKey};
+
+    fn test_private_key(seed: u8) -> PrivateKey {
+        PrivateKey::from(Ed25519Keypair::from(Ed25519PrivateKey::from_bytes(
+            &[seed; 32],
+        )))
+    }
+
+    #[test]
+    fn issues_certificates_that_validate_against_the_ca() {
+        let ca = CertificateAuthority::new(test_private_key(1));
+        let subject = PublicKey::from(&test_private_key(2));
+        let permissions = CertificatePermissions {
+            force_command: Some("uptime".to_string()),
+            source_address: Some("203.0.113.7/32".to_string()),
+            extensions: vec!["permit-pty"],
+        };
+        let principals = vec!["argon".to_string()];
+
+        let certificate = ca
+            .issue(
+                &subject,
+                &CertificateRequest {
+                    key_id: "argon-app-ssh:1000".to_string(),
+                    serial: 42,
+                    principals: &principals,
+                    valid_after: 1_000,
+                    valid_before: 4_600,
+                    permissions: &permissions,
+                },
+            )
+            .expect("certificate should be issued");
+
+        let ca_fingerprint = PublicKey::from(&ca.key).fingerprint(HashAlg::Sha256);
+        certificate
+            .validate_at(2_000, [&ca_fingerprint])
+            .expect("certificate should be valid inside its window");
+        assert!(certificate.validate_at(5_000, [&ca_fingerprint]).is_err());
+        assert_eq!(certificate.serial(), 42);
+        assert_eq!(certificate.valid_principals(), principals.as_slice());
+        assert_eq!(
+            certificate
+                .critical_options()
+                .get("force-command")
+                .map(String::as_str),
+            Some("uptime")
+        );
+        assert!(certificate.extensions().contains_key("permit-pty"));
+        assert!(
+            !certificate
+                .extensions()
+                .contains_key("permit-port-forwarding")
+        );
+
+        let other_ca = PublicKey::from(&test_private_key(3)).fingerprint(HashAlg::Sha256);
+        assert!(certificate.validate_at(2_000, [&other_ca]).is_err());
+    }
+
+    #[test]
+    fn trust_commands_keep_an_existing_krl_and_can_be_undone() {
+        let ca = CertificateAuthority::new(test_private_key(1));
+        let ca_public_key = ca.public_key_openssh().expect("public key should encode");
+
+        let install = ca.install_trust_command().expect("install should build");
+        assert!(install.contains("! grep -rqsiE '^[[:space:]]*RevokedKeys[[:space:]]'"));
+        assert!(install.contains(CA_TRUSTED_MARKER));
+
+        let uninstall = ca
+            .uninstall_trust_command()
+            .expect("uninstall should build");
+        assert!(uninstall.contains(&format!("grep -v -F '{ca_public_key}'")));
+        assert!(uninstall.contains(&format!("rm -f {SYSTEM_SSHD_DROP_IN_PATH}")));
+    }
+
+    #[test]
+    fn builds_krl_revocation_spec() {
+        let command = revoke_serials_command(&[7, 9]);
+        assert!(command.contains("printf 'serial: 7\\nserial: 9\\n'"));
+        assert!(command.contains(&format!("-f {SYSTEM_KRL_PATH}")));
+    }
+}
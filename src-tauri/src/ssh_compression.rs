@@ -0,0 +1,207 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+const ZSTD_LEVEL: i32 = 3;
+
+/// Prints the name of each codec the server can run, one per line.
+pub const DETECT_COMMAND: &str =
+    "for codec in zstd gzip; do command -v $codec >/dev/null 2>&1 && echo $codec; done; true";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Compression {
+    #[default]
+    None,
+    /// zstd when the server has it, otherwise gzip.
+    Auto,
+    Zstd,
+    Gzip,
+}
+
+impl Compression {
+    /// Picks a codec from the output of `DETECT_COMMAND`. A requested codec the server lacks
+    /// falls back to the other one, then to no compression.
+    pub fn choose(self, available: &str) -> Option<Codec> {
+        let preference = match self {
+            Compression::None => return None,
+            Compression::Auto | Compression::Zstd => [Codec::Zstd, Codec::Gzip],
+            Compression::Gzip => [Codec::Gzip, Codec::Zstd],
+        };
+        preference
+            .into_iter()
+            .find(|codec| available.lines().any(|line| line.trim() == codec.name()))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Codec {
+    Zstd,
+    Gzip,
+}
+
+impl Codec {
+    pub fn name(self) -> &'static str {
+        match self {
+            Codec::Zstd => "zstd",
+            Codec::Gzip => "gzip",
+        }
+    }
+
+    /// Compresses stdin to stdout on the server.
+    pub fn compress_command(self) -> &'static str {
+        match self {
+            Codec::Zstd => "zstd -q -c -3",
+            Codec::Gzip => "gzip -c",
+        }
+    }
+
+    /// Decompresses stdin to stdout on the server.
+    pub fn decompress_command(self) -> &'static str {
+        match self {
+            Codec::Zstd => "zstd -q -d -c",
+            Codec::Gzip => "gzip -d -c",
+        }
+    }
+
+    pub fn encoder(self) -> Result<Encoder> {
+        Ok(match self {
+            Codec::Zstd => {
+                Encoder::Zstd(zstd::stream::write::Encoder::new(Vec::new(), ZSTD_LEVEL)?)
+            }
+            Codec::Gzip => Encoder::Gzip(flate2::write::GzEncoder::new(
+                Vec::new(),
+                flate2::Compression::default(),
+            )),
+        })
+    }
+
+    pub fn decoder(self) -> Result<Decoder> {
+        Ok(match self {
+            Codec::Zstd => Decoder::Zstd(zstd::stream::zio::Writer::new(
+                Vec::new(),
+                zstd::stream::raw::Decoder::new()?,
+            )),
+            Codec::Gzip => Decoder::Gzip(flate2::write::GzDecoder::new(Vec::new())),
+        })
+    }
+}
+
+/// Compresses a stream chunk by chunk. Each call returns whatever output is ready to send,
+/// which may be empty while the codec buffers input.
+pub enum Encoder {
+    Zstd(zstd::stream::write::Encoder<'static, Vec<u8>>),
+    Gzip(flate2::write::GzEncoder<Vec<u8>>),
+}
+
+impl Encoder {
+    pub fn compress(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(match self {
+            Encoder::Zstd(encoder) => {
+                encoder.write_all(data)?;
+                std::mem::take(encoder.get_mut())
+            }
+            Encoder::Gzip(encoder) => {
+                encoder.write_all(data)?;
+                std::mem::take(encoder.get_mut())
+            }
+        })
+    }
+
+    /// Returns the remaining output, including the end-of-stream trailer.
+    pub fn finish(self) -> Result<Vec<u8>> {
+        Ok(match self {
+            Encoder::Zstd(encoder) => encoder.finish()?,
+            Encoder::Gzip(encoder) => encoder.finish()?,
+        })
+    }
+}
+
+/// The inverse of `Encoder`, fed with chunks as they arrive.
+pub enum Decoder {
+    // The raw writer, unlike `write::Decoder`, reports a stream that ends mid-frame.
+    Zstd(zstd::stream::zio::Writer<Vec<u8>, zstd::stream::raw::Decoder<'static>>),
+    Gzip(flate2::write::GzDecoder<Vec<u8>>),
+}
+
+impl Decoder {
+    pub fn decompress(&mut self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(match self {
+            Decoder::Zstd(decoder) => {
+                decoder.write_all(data)?;
+                std::mem::take(decoder.writer_mut())
+            }
+            Decoder::Gzip(decoder) => {
+                decoder.write_all(data)?;
+                std::mem::take(decoder.get_mut())
+            }
+        })
+    }
+
+    /// Fails if the stream was cut off before its last frame ended.
+    pub fn finish(self) -> Result<Vec<u8>> {
+        Ok(match self {
+            Decoder::Zstd(mut decoder) => {
+                decoder.finish()?;
+                decoder.into_inner().0
+            }
+            Decoder::Gzip(decoder) => decoder.finish()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chooses_a_codec_the_server_has() {
+        assert_eq!(Compression::Auto.choose("zstd\ngzip\n"), Some(Codec::Zstd));
+        assert_eq!(Compression::Auto.choose("gzip\n"), Some(Codec::Gzip));
+        assert_eq!(Compression::Gzip.choose("zstd\ngzip\n"), Some(Codec::Gzip));
+        assert_eq!(Compression::Gzip.choose("zstd\n"), Some(Codec::Zstd));
+        assert_eq!(Compression::Zstd.choose(""), None);
+        assert_eq!(Compression::None.choose("zstd\ngzip\n"), None);
+    }
+
+    #[test]
+    fn round_trips_chunked_streams() {
+        let source = (0..200_000u32)
+            .flat_map(|n| (n % 251).to_le_bytes())
+            .collect::<Vec<_>>();
+        for codec in [Codec::Zstd, Codec::Gzip] {
+            let mut encoder = codec.encoder().expect("encoder should start");
+            let mut wire = Vec::new();
+            for chunk in source.chunks(64 * 1024) {
+                wire.extend(encoder.compress(chunk).expect("chunk should compress"));
+            }
+            wire.extend(encoder.finish().expect("stream should finish"));
+            assert!(wire.len() < source.len() / 4, "{codec:?}");
+
+            let mut decoder = codec.decoder().expect("decoder should start");
+            let mut decoded = Vec::new();
+            for chunk in wire.chunks(1000) {
+                decoded.extend(decoder.decompress(chunk).expect("chunk should decompress"));
+            }
+            decoded.extend(decoder.finish().expect("stream should finish"));
+            assert_eq!(decoded, source, "{codec:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_streams() {
+        let source = (0..50_000u32)
+            .flat_map(u32::to_le_bytes)
+            .collect::<Vec<_>>();
+        for codec in [Codec::Zstd, Codec::Gzip] {
+            let mut encoder = codec.encoder().expect("encoder should start");
+            let mut wire = encoder.compress(&source).expect("stream should compress");
+            wire.extend(encoder.finish().expect("stream should finish"));
+
+            let mut decoder = codec.decoder().expect("decoder should start");
+            let _ = decoder.decompress(&wire[..wire.len() - 8]);
+            assert!(decoder.finish().is_err(), "{codec:?}");
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use crate::security::Security;
+use anyhow::{Context, Result};
+use russh::client::{AuthResult, Handle, Handler};
+use russh::keys::agent::client::AgentClient;
+use russh::keys::{HashAlg, PrivateKey, PrivateKeyWithHashAlg, PublicKey, decode_secret_key};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::AppHandle;
+
+/// Where a connection gets the key it authenticates with.
+#[derive(Clone, Default, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum KeySource {
+    /// The `//ssh-ed25519//1` key derived from the wallet mnemonic.
+    #[default]
+    Mnemonic,
+    /// A key held by the local ssh-agent, which also covers hardware-backed keys.
+    /// Without `public_key` the agent's first identity is used.
+    Agent { public_key: Option<String> },
+    /// An OpenSSH private key file, optionally protected by a passphrase.
+    KeyFile {
+        path: PathBuf,
+        passphrase: Option<String>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IdentityKind {
+    Mnemonic,
+    Agent,
+    KeyFile,
+}
+
+/// What a connection authenticated with, safe to show in the UI.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityInfo {
+    pub kind: IdentityKind,
+    pub public_key: String,
+    pub fingerprint: String,
+    /// Key comment for agent identities, file path for key files.
+    pub label: Option<String>,
+}
+
+#[derive(Clone)]
+enum Signer {
+    PrivateKey(Arc<PrivateKey>),
+    // The agent socket is reopened per authentication so reconnects keep working.
+    Agent,
+}
+
+#[derive(Clone)]
+pub struct Identity {
+    info: IdentityInfo,
+    public_key: PublicKey,
+    signer: Signer,
+}
+
+impl PartialEq for Identity {
+    fn eq(&self, other: &Self) -> bool {
+        self.info == other.info
+    }
+}
+
+impl Identity {
+    pub fn from_private_key(
+        kind: IdentityKind,
+        private_key_openssh: &SecretString,
+        passphrase: Option<&SecretString>,
+        label: Option<String>,
+    ) -> Result<Self> {
+        let private_key = decode_secret_key(
+            private_key_openssh.expose_secret(),
+            passphrase.map(|passphrase| passphrase.expose_secret()),
+        )?;
+        let public_key = PublicKey::from(&private_key);
+        Ok(Self {
+            info: identity_info(kind, &public_key, label)?,
+            public_key,
+            signer: Signer::PrivateKey(Arc::new(private_key)),
+        })
+    }
+
+    pub fn mnemonic(app: &AppHandle) -> Result<Self> {
+        let private_key = Security::expose_private_key_openssh(app)?;
+        Self::from_private_key(IdentityKind::Mnemonic, &private_key, None, None)
+    }
+
+    pub async fn resolve(app: &AppHandle, source: &KeySource) -> Result<Self> {
+        match source {
+            KeySource::Mnemonic => Self::mnemonic(app),
+            KeySource::Agent { public_key } => {
+                let identities = connect_agent().await?.request_identities().await?;
+                let public_key = match public_key {
+                    Some(wanted) => {
+                        let wanted = PublicKey::from_openssh(wanted.trim())?;
+                        identities
+                            .into_iter()
+                            .find(|key| key.key_data() == wanted.key_data())
+                            .context("The ssh-agent does not hold the requested key")?
+                    }
+                    None => identities
+                        .into_iter()
+                        .next()
+                        .context("The ssh-agent has no identities")?,
+                };
+                let label = Some(public_key.comment().to_string()).filter(|c| !c.is_empty());
+                Ok(Self {
+                    info: identity_info(IdentityKind::Agent, &public_key, label)?,
+                    public_key,
+                    signer: Signer::Agent,
+                })
+            }
+            KeySource::KeyFile { path, passphrase } => {
+                let private_key = tokio::fs::read_to_string(path)
+                    .await
+                    .with_context(|| format!("Reading key file {}", path.display()))?;
+                let passphrase = passphrase
+                    .as_ref()
+                    .map(|passphrase| SecretString::new(passphrase.clone().into()));
+                Self::from_private_key(
+                    IdentityKind::KeyFile,
+                    &SecretString::new(private_key.into()),
+                    passphrase.as_ref(),
+                    Some(path.display().to_string()),
+                )
+                .with_context(|| format!("Decoding key file {}", path.display()))
+            }
+        }
+    }
+
+    pub fn info(&self) -> &IdentityInfo {
+        &self.info
+    }
+
+    pub async fn authenticate<H: Handler>(
+        &self,
+        client: &mut Handle<H>,
+        username: &str,
+    ) -> Result<()> {
+        // Only RSA keys care; other algorithms ignore the hash.
+        let hash_alg = client.best_supported_rsa_hash().await?.flatten();
+        let auth_res = match &self.signer {
+            Signer::PrivateKey(private_key) => {
+                client
+                    .authenticate_publickey(
+                        username,
+                        PrivateKeyWithHashAlg::new(private_key.clone(), hash_alg),
+                    )
+                    .await?
+            }
+            Signer::Agent => {
+                let mut agent = connect_agent().await?;
+                client
+                    .authenticate_publickey_with(
+                        username,
+                        self.public_key.clone(),
+                        hash_alg,
+                        &mut agent,
+                    )
+                    .await?
+            }
+        };
+
+        if let AuthResult::Failure {
+            remaining_methods,
+            partial_success,
+        } = auth_res
+        {
+            anyhow::bail!(
+                "Authentication (with publickey {}) failed for {username}: {remaining_methods:?} (partial success: {partial_success})",
+                self.info.fingerprint
+            );
+        }
+        Ok(())
+    }
+}
+
+fn identity_info(
+    kind: IdentityKind,
+    public_key: &PublicKey,
+    label: Option<String>,
+) -> Result<IdentityInfo> {
+    // Compare and display keys without their comment.
+    let mut bare_key = public_key.clone();
+    bare_key.set_comment("");
+    Ok(IdentityInfo {
+        kind,
+        public_key: bare_key.to_openssh()?,
+        fingerprint: public_key.fingerprint(HashAlg::Sha256).to_string(),
+        label,
+    })
+}
+
+#[cfg(unix)]
+async fn connect_agent() -> Result<AgentClient<tokio::net::UnixStream>> {
+    AgentClient::connect_env()
+        .await
+        .context("Could not reach the ssh-agent via SSH_AUTH_SOCK")
+}
+
+#[cfg(not(unix))]
+async fn connect_agent() -> Result<AgentClient<tokio::net::TcpStream>> {
+    anyhow::bail!("ssh-agent authentication is only supported on macOS and Linux")
+}
+
+#[tauri::command]
+pub async fn ssh_list_agent_identities() -> Result<Vec<IdentityInfo>, String> {
+    let identities = async {
+        let keys = connect_agent().await?.request_identities().await?;
+        keys.iter()
+            .map(|key| {
+                let label = Some(key.comment().to_string()).filter(|c| !c.is_empty());
+                identity_info(IdentityKind::Agent, key, label)
+            })
+            .collect::<Result<Vec<_>>>()
+    };
+    identities.await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use russh::keys::ssh_key::LineEnding;
+    use russh::keys::ssh_key::private::{Ed25519Keypair, Ed25519PrivateKey};
+
+    #[test]
+    fn decodes_passphrase_protected_key_files() {
+        let keypair = Ed25519Keypair::from(Ed25519PrivateKey::from_bytes(&[7; 32]));
+        let private_key = PrivateKey::from(keypair);
+        let encrypted = private_key
+            .encrypt(&mut russh::keys::ssh_key::rand_core::OsRng, "correct horse")
+            .expect("key should encrypt")
+            .to_openssh(LineEnding::LF)
+            .expect("key should encode")
+            .to_string();
+        let encrypted = SecretString::new(encrypted.into());
+
+        let identity = Identity::from_private_key(
+            IdentityKind::KeyFile,
+            &encrypted,
+            Some(&SecretString::new("correct horse".into())),
+            Some("/keys/id_ed25519".to_string()),
+        )
+        .expect("key should decrypt");
+        assert_eq!(identity.info().kind, IdentityKind::KeyFile);
+        assert_eq!(
+            identity.info().fingerprint,
+            PublicKey::from(&private_key)
+                .fingerprint(HashAlg::Sha256)
+                .to_string()
+        );
+
+        assert!(Identity::from_private_key(IdentityKind::KeyFile, &encrypted, None, None).is_err());
+        assert!(
+            Identity::from_private_key(
+                IdentityKind::KeyFile,
+                &encrypted,
+                Some(&SecretString::new("wrong".into())),
+                None
+            )
+            .is_err()
+        );
+    }
+}
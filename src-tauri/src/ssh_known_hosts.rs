@@ -0,0 +1,367 @@
+use crate::ssh::{JumpHostTarget, SSH};
+use crate::ssh_identity::{Identity, KeySource};
+use crate::utils::Utils;
+use anyhow::Result;
+use lazy_static::lazy_static;
+use russh::keys::{HashAlg, PublicKey};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+
+const KNOWN_HOSTS_FILE_NAME: &str = "ssh_known_hosts.json";
+const HOST_KEY_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+lazy_static! {
+    // Serializes read-modify-write cycles on the known hosts file within this process.
+    static ref KNOWN_HOSTS_FILE_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KnownHostEntry {
+    pub host: String,
+    pub algorithm: String,
+    pub public_key: String,
+    pub fingerprint: String,
+    pub pinned_at: i64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KnownHostInspection {
+    pub host: String,
+    pub pinned: Option<KnownHostEntry>,
+    pub presented_algorithm: String,
+    pub presented_public_key: String,
+    pub presented_fingerprint: String,
+    pub matches_pinned: bool,
+}
+
+/// Raised when a server presents a different host key than the one pinned for its address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HostKeyChangedError {
+    pub host: String,
+    pub pinned_fingerprint: String,
+    pub presented_fingerprint: String,
+}
+
+impl fmt::Display for HostKeyChangedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "SSHHostKeyChanged: {} presented {} but {} is pinned",
+            self.host, self.presented_fingerprint, self.pinned_fingerprint
+        )
+    }
+}
+
+impl std::error::Error for HostKeyChangedError {}
+
+#[derive(Default, Serialize, Deserialize)]
+struct KnownHostsFile {
+    hosts: BTreeMap<String, KnownHostEntry>,
+}
+
+/// Trust-on-first-use host key store, kept per instance and keyed by `host_key`.
+#[derive(Clone, Debug)]
+pub struct KnownHosts {
+    path: PathBuf,
+}
+
+impl KnownHosts {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn for_instance(app: &AppHandle) -> Self {
+        Self::new(Utils::get_absolute_config_instance_dir(app).join(KNOWN_HOSTS_FILE_NAME))
+    }
+
+    pub fn list(&self) -> Result<Vec<KnownHostEntry>> {
+        let _guard = lock_known_hosts_file();
+        Ok(self.read()?.hosts.into_values().collect())
+    }
+
+    pub fn get(&self, host: &str) -> Result<Option<KnownHostEntry>> {
+        let _guard = lock_known_hosts_file();
+        Ok(self.read()?.hosts.remove(host))
+    }
+
+    /// Pins the key on first contact and rejects any later key that differs from the pin.
+    pub fn verify_or_pin(&self, host: &str, key: &PublicKey) -> Result<KnownHostEntry> {
+        let _guard = lock_known_hosts_file();
+        let mut file = self.read()?;
+        let presented = known_host_entry(host, key)?;
+
+        if let Some(pinned) = file.hosts.get(host) {
+            if pinned.public_key != presented.public_key {
+                return Err(HostKeyChangedError {
+                    host: host.to_string(),
+                    pinned_fingerprint: pinned.fingerprint.clone(),
+                    presented_fingerprint: presented.fingerprint,
+                }
+                .into());
+            }
+            return Ok(pinned.clone());
+        }
+
+        log::info!("Pinning SSH host key for {host}: {}", presented.fingerprint);
+        file.hosts.insert(host.to_string(), presented.clone());
+        self.write(&file)?;
+        Ok(presented)
+    }
+
+    /// Replaces the pinned key for a host, e.g. after a server was intentionally rebuilt.
+    pub fn repin(&self, host: &str, key: &PublicKey) -> Result<KnownHostEntry> {
+        let _guard = lock_known_hosts_file();
+        let mut file = self.read()?;
+        let entry = known_host_entry(host, key)?;
+        if let Some(previous) = file.hosts.get(host) {
+            log::warn!(
+                "Re-pinning SSH host key for {host}: {} -> {}",
+                previous.fingerprint,
+                entry.fingerprint
+            );
+        }
+        file.hosts.insert(host.to_string(), entry.clone());
+        self.write(&file)?;
+        Ok(entry)
+    }
+
+    fn read(&self) -> Result<KnownHostsFile> {
+        if !self.path.exists() {
+            return Ok(KnownHostsFile::default());
+        }
+        let raw = fs::read_to_string(&self.path)?;
+        Ok(serde_json::from_str(&raw)?)
+    }
+
+    fn write(&self, file: &KnownHostsFile) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_string_pretty(file)?)?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+fn lock_known_hosts_file() -> std::sync::MutexGuard<'static, ()> {
+    KNOWN_HOSTS_FILE_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn known_host_entry(host: &str, key: &PublicKey) -> Result<KnownHostEntry> {
+    Ok(KnownHostEntry {
+        host: host.to_string(),
+        algorithm: key.algorithm().as_str().to_string(),
+        public_key: key.to_openssh()?,
+        fingerprint: key.fingerprint(HashAlg::Sha256).to_string(),
+        pinned_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64,
+    })
+}
+
+/// `host:port`, followed by the jump hosts that reach it: the same private address behind two
+/// bastions is two different servers.
+pub fn host_key(host: &str, port: u16, via: &[String]) -> String {
+    match via {
+        [] => format!("{host}:{port}"),
+        via => format!("{host}:{port} via {}", via.join(" > ")),
+    }
+}
+
+/// Connects through the jump chain, which is verified against the pins, and returns the key the
+/// target presents along with the key it is pinned under.
+async fn fetch_presented_key(
+    app: &AppHandle,
+    known_hosts: &KnownHosts,
+    host: &str,
+    port: u16,
+    key_source: Option<KeySource>,
+    jump_hosts: Option<Vec<JumpHostTarget>>,
+) -> Result<(String, PublicKey)> {
+    let jump_hosts = match jump_hosts.unwrap_or_default() {
+        targets if targets.is_empty() => Vec::new(),
+        targets => {
+            let identity = Identity::resolve(app, &key_source.unwrap_or_default()).await?;
+            JumpHostTarget::resolve_all(app, targets, &identity).await?
+        }
+    };
+    let via = jump_hosts.iter().map(|hop| hop.host()).collect::<Vec<_>>();
+    let presented =
+        SSH::fetch_host_key(host, port, &jump_hosts, known_hosts, HOST_KEY_FETCH_TIMEOUT).await?;
+    Ok((host_key(host, port, &via), presented))
+}
+
+#[tauri::command]
+pub async fn ssh_list_known_hosts(app: AppHandle) -> Result<Vec<KnownHostEntry>, String> {
+    KnownHosts::for_instance(&app)
+        .list()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn ssh_inspect_known_host(
+    app: AppHandle,
+    host: &str,
+    port: u16,
+    key_source: Option<KeySource>,
+    jump_hosts: Option<Vec<JumpHostTarget>>,
+) -> Result<KnownHostInspection, String> {
+    let known_hosts = KnownHosts::for_instance(&app);
+    let (address, presented) =
+        fetch_presented_key(&app, &known_hosts, host, port, key_source, jump_hosts)
+            .await
+            .map_err(|e| e.to_string())?;
+    let pinned = known_hosts.get(&address).map_err(|e| e.to_string())?;
+    let presented = known_host_entry(&address, &presented).map_err(|e| e.to_string())?;
+
+    Ok(KnownHostInspection {
+        matches_pinned: pinned
+            .as_ref()
+            .is_some_and(|pinned| pinned.public_key == presented.public_key),
+        host: address,
+        pinned,
+        presented_algorithm: presented.algorithm,
+        presented_public_key: presented.public_key,
+        presented_fingerprint: presented.fingerprint,
+    })
+}
+
+/// Pins the key the server presents now, but only if it matches the fingerprint the user reviewed.
+#[tauri::command]
+pub async fn ssh_repin_known_host(
+    app: AppHandle,
+    host: &str,
+    port: u16,
+    key_source: Option<KeySource>,
+    jump_hosts: Option<Vec<JumpHostTarget>>,
+    expected_fingerprint: String,
+) -> Result<KnownHostEntry, String> {
+    let known_hosts = KnownHosts::for_instance(&app);
+    let (address, presented) =
+        fetch_presented_key(&app, &known_hosts, host, port, key_source, jump_hosts)
+            .await
+            .map_err(|e| e.to_string())?;
+    let fingerprint = presented.fingerprint(HashAlg::Sha256).to_string();
+    if fingerprint != expected_fingerprint.trim() {
+        return Err(format!(
+            "{address} now presents {fingerprint}, not the expected {expected_fingerprint}"
+        ));
+    }
+
+    known_hosts
+        .repin(&address, &presented)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use russh::keys::PrivateKey;
+    use russh::keys::ssh_key::private::{Ed25519Keypair, Ed25519PrivateKey};
+
+    #[test]
+    fn pins_first_key_and_rejects_a_changed_key() {
+        let test_dir = unique_test_dir("pins-first-key");
+        let known_hosts = KnownHosts::new(test_dir.join(KNOWN_HOSTS_FILE_NAME));
+        let first = test_public_key(1);
+        let second = test_public_key(2);
+
+        let pinned = known_hosts
+            .verify_or_pin("10.0.0.1:22", &first)
+            .expect("first key should be pinned");
+        assert_eq!(
+            known_hosts
+                .verify_or_pin("10.0.0.1:22", &first)
+                .expect("same key should verify"),
+            pinned
+        );
+
+        let error = known_hosts
+            .verify_or_pin("10.0.0.1:22", &second)
+            .expect_err("changed key should be rejected");
+        let error = error
+            .downcast_ref::<HostKeyChangedError>()
+            .expect("error should be a host key change");
+        assert_eq!(error.pinned_fingerprint, pinned.fingerprint);
+
+        // Other ports on the same host are pinned independently.
+        known_hosts
+            .verify_or_pin("10.0.0.1:2222", &second)
+            .expect("different address should pin separately");
+        assert_eq!(known_hosts.list().expect("hosts should list").len(), 2);
+
+        fs::remove_dir_all(&test_dir).expect("test dir should be removed");
+    }
+
+    #[test]
+    fn keys_hosts_behind_jump_hosts_by_their_chain() {
+        assert_eq!(host_key("10.0.0.1", 22, &[]), "10.0.0.1:22");
+        assert_eq!(
+            host_key(
+                "10.0.0.1",
+                22,
+                &["bastion:22".to_string(), "inner:2222".to_string()]
+            ),
+            "10.0.0.1:22 via bastion:22 > inner:2222"
+        );
+        assert_ne!(
+            host_key("10.0.0.1", 22, &["bastion-a:22".to_string()]),
+            host_key("10.0.0.1", 22, &["bastion-b:22".to_string()])
+        );
+    }
+
+    #[test]
+    fn repin_replaces_the_pinned_key() {
+        let test_dir = unique_test_dir("repin-replaces-key");
+        let known_hosts = KnownHosts::new(test_dir.join(KNOWN_HOSTS_FILE_NAME));
+        let first = test_public_key(1);
+        let second = test_public_key(2);
+
+        known_hosts
+            .verify_or_pin("10.0.0.1:22", &first)
+            .expect("first key should be pinned");
+        let repinned = known_hosts
+            .repin("10.0.0.1:22", &second)
+            .expect("key should be re-pinned");
+        assert_eq!(
+            known_hosts
+                .get("10.0.0.1:22")
+                .expect("host should load")
+                .expect("host should be pinned"),
+            repinned
+        );
+        known_hosts
+            .verify_or_pin("10.0.0.1:22", &second)
+            .expect("re-pinned key should verify");
+
+        fs::remove_dir_all(&test_dir).expect("test dir should be removed");
+    }
+
+    fn test_public_key(seed: u8) -> PublicKey {
+        let keypair = Ed25519Keypair::from(Ed25519PrivateKey::from_bytes(&[seed; 32]));
+        PublicKey::from(&PrivateKey::from(keypair))
+    }
+
+    fn unique_test_dir(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("system time should be after epoch")
+            .as_nanos();
+
+        std::env::temp_dir().join(format!(
+            "argon-known-hosts-{name}-{}-{nanos}",
+            std::process::id()
+        ))
+    }
+}
@@ -0,0 +1,324 @@
+use crate::ssh::shell_quote;
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Once set, raw command strings are refused for the rest of the process.
+static STRICT_MODE: AtomicBool = AtomicBool::new(false);
+// The system logs and the `logs` folder of the default work dir, where installer steps log to.
+const LOG_ROOTS: [&str; 2] = ["/var/log/", "~/logs/"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ParamKind {
+    /// A systemd unit or container name: letters, digits and `_.@:-`.
+    Name,
+    /// A file below one of the `LOG_ROOTS`, without `.`, `..` or empty segments.
+    LogPath,
+    /// An integer within `min..=max`.
+    Integer { min: i64, max: i64 },
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationParam {
+    pub name: &'static str,
+    pub kind: ParamKind,
+}
+
+/// A vetted remote operation. `{name}` placeholders in the template are replaced by the
+/// validated, shell-quoted argument of the same name.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Operation {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub params: &'static [OperationParam],
+    #[serde(skip)]
+    template: &'static str,
+}
+
+const SERVICE: OperationParam = OperationParam {
+    name: "service",
+    kind: ParamKind::Name,
+};
+const CONTAINER: OperationParam = OperationParam {
+    name: "container",
+    kind: ParamKind::Name,
+};
+const LINES: OperationParam = OperationParam {
+    name: "lines",
+    kind: ParamKind::Integer {
+        min: 1,
+        max: 10_000,
+    },
+};
+const PATH: OperationParam = OperationParam {
+    name: "path",
+    kind: ParamKind::LogPath,
+};
+
+const OPERATIONS: &[Operation] = &[
+    Operation {
+        name: "disk_usage",
+        description: "Show disk usage per filesystem",
+        params: &[],
+        template: "df -h",
+    },
+    Operation {
+        name: "memory_usage",
+        description: "Show memory usage",
+        params: &[],
+        template: "free -m",
+    },
+    Operation {
+        name: "service_status",
+        description: "Show the status of a systemd service",
+        params: &[SERVICE],
+        template: "systemctl status --no-pager {service}",
+    },
+    Operation {
+        name: "restart_service",
+        description: "Restart a systemd service",
+        params: &[SERVICE],
+        template: "sudo -n systemctl restart {service}",
+    },
+    Operation {
+        name: "tail_service_journal",
+        description: "Show the latest journal lines of a systemd service",
+        params: &[SERVICE, LINES],
+        template: "journalctl --no-pager -u {service} -n {lines}",
+    },
+    Operation {
+        name: "tail_log",
+        description: "Show the last lines of a log file under /var/log or ~/logs",
+        params: &[PATH, LINES],
+        template: "tail -n {lines} {path}",
+    },
+    Operation {
+        name: "list_containers",
+        description: "List docker containers",
+        params: &[],
+        template: "docker ps --all",
+    },
+    Operation {
+        name: "container_logs",
+        description: "Show the latest log lines of a docker container",
+        params: &[CONTAINER, LINES],
+        template: "docker logs --tail {lines} {container}",
+    },
+    Operation {
+        name: "restart_container",
+        description: "Restart a docker container",
+        params: &[CONTAINER],
+        template: "docker restart {container}",
+    },
+];
+
+pub fn list_operations() -> &'static [Operation] {
+    OPERATIONS
+}
+
+/// Builds the command for `name`, rejecting unknown operations and arguments that are missing,
+/// unexpected or of the wrong kind.
+pub fn render(name: &str, args: &BTreeMap<String, Value>) -> Result<String> {
+    let operation = OPERATIONS
+        .iter()
+        .find(|operation| operation.name == name)
+        .ok_or_else(|| anyhow::anyhow!("Unknown SSH operation: {name}"))?;
+    if let Some(unexpected) = args
+        .keys()
+        .find(|arg| !operation.params.iter().any(|param| param.name == *arg))
+    {
+        anyhow::bail!("Unexpected argument for {name}: {unexpected}");
+    }
+
+    let mut values = BTreeMap::new();
+    for param in operation.params {
+        let value = args
+            .get(param.name)
+            .ok_or_else(|| anyhow::anyhow!("Missing argument for {name}: {}", param.name))?;
+        values.insert(param.name, shell_argument(&validate(param, value)?));
+    }
+
+    // Substitute in one pass so an argument containing `{other}` is never expanded again.
+    let mut command = String::new();
+    let mut rest = operation.template;
+    while let Some(start) = rest.find('{') {
+        let end = start
+            + rest[start..]
+                .find('}')
+                .expect("templates close placeholders");
+        command.push_str(&rest[..start]);
+        command.push_str(&values[&rest[start + 1..end]]);
+        rest = &rest[end + 1..];
+    }
+    command.push_str(rest);
+    Ok(command)
+}
+
+fn validate(param: &OperationParam, value: &Value) -> Result<String> {
+    let invalid = || anyhow::anyhow!("Invalid value for {}: {value}", param.name);
+    match param.kind {
+        ParamKind::Name => {
+            let name = value.as_str().ok_or_else(invalid)?;
+            let valid = !name.is_empty()
+                && !name.starts_with('-')
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "_.@:-".contains(c));
+            valid.then(|| name.to_string()).ok_or_else(invalid)
+        }
+        ParamKind::LogPath => {
+            let path = value.as_str().ok_or_else(invalid)?;
+            let relative = LOG_ROOTS
+                .iter()
+                .find_map(|root| path.strip_prefix(root))
+                .ok_or_else(invalid)?;
+            let valid = !path.contains('\0')
+                && relative
+                    .split('/')
+                    .all(|segment| !matches!(segment, "" | "." | ".."));
+            valid.then(|| path.to_string()).ok_or_else(invalid)
+        }
+        ParamKind::Integer { min, max } => {
+            let number = value.as_i64().ok_or_else(invalid)?;
+            (min..=max)
+                .contains(&number)
+                .then(|| number.to_string())
+                .ok_or_else(invalid)
+        }
+    }
+}
+
+/// Quotes a validated value, leaving a leading `~/` for the remote shell to expand.
+fn shell_argument(value: &str) -> String {
+    match value.strip_prefix("~/") {
+        Some(relative) => format!("\"$HOME\"/{}", shell_quote(relative)),
+        None => shell_quote(value),
+    }
+}
+
+pub fn enable_strict_mode() {
+    if !STRICT_MODE.swap(true, Ordering::SeqCst) {
+        log::info!("SSH strict mode enabled; only registered operations may run");
+    }
+}
+
+pub fn is_strict_mode() -> bool {
+    STRICT_MODE.load(Ordering::SeqCst)
+}
+
+/// Called by every command that runs a raw command string.
+pub fn ensure_raw_commands_allowed() -> Result<(), String> {
+    if is_strict_mode() {
+        return Err("SSHStrictMode: raw commands are disabled, use a registered operation".into());
+    }
+    Ok(())
+}
+
+/// Called by commands that can change the server as freely as a raw command: writing files to
+/// arbitrary paths, forwarding ports and installing access keys.
+pub fn ensure_unrestricted_action_allowed(action: &str) -> Result<(), String> {
+    if is_strict_mode() {
+        return Err(format!("SSHStrictMode: {action} is disabled"));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn ssh_list_operations() -> Result<&'static [Operation], String> {
+    Ok(list_operations())
+}
+
+/// Strict mode cannot be turned off again without restarting the app.
+#[tauri::command]
+pub async fn ssh_enable_strict_mode() -> Result<bool, String> {
+    enable_strict_mode();
+    Ok(is_strict_mode())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn args(value: Value) -> BTreeMap<String, Value> {
+        serde_json::from_value(value).expect("args should be an object")
+    }
+
+    #[test]
+    fn renders_operations_with_quoted_arguments() {
+        assert_eq!(
+            render(
+                "container_logs",
+                &args(json!({ "container": "argon-miner", "lines": 200 }))
+            )
+            .expect("operation should render"),
+            "docker logs --tail '200' 'argon-miner'"
+        );
+        assert_eq!(
+            render(
+                "tail_log",
+                &args(json!({ "path": "/var/log/it's.log", "lines": 5 }))
+            )
+            .expect("operation should render"),
+            "tail -n '5' '/var/log/it'\\''s.log'"
+        );
+        assert_eq!(
+            render(
+                "tail_log",
+                &args(json!({ "path": "/var/log/{lines}.log", "lines": 5 }))
+            )
+            .expect("operation should render"),
+            "tail -n '5' '/var/log/{lines}.log'"
+        );
+        assert_eq!(
+            render(
+                "tail_log",
+                &args(json!({ "path": "~/logs/step-1.log", "lines": 5 }))
+            )
+            .expect("operation should render"),
+            "tail -n '5' \"$HOME\"/'logs/step-1.log'"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_operation_arguments() {
+        let rejected = [
+            ("restart_service", json!({ "service": "argon; rm -rf /" })),
+            ("restart_service", json!({ "service": "--help" })),
+            ("restart_service", json!({})),
+            ("restart_service", json!({ "service": "argon", "extra": 1 })),
+            (
+                "tail_log",
+                json!({ "path": "/var/log/../../etc/shadow", "lines": 5 }),
+            ),
+            ("tail_log", json!({ "path": "/etc/shadow", "lines": 5 })),
+            ("tail_log", json!({ "path": "/var/log/", "lines": 5 })),
+            ("tail_log", json!({ "path": "/var/logs/x.log", "lines": 5 })),
+            (
+                "tail_log",
+                json!({ "path": "~/logs/../.ssh/id_ed25519", "lines": 5 }),
+            ),
+            (
+                "tail_log",
+                json!({ "path": "~/.ssh/id_ed25519", "lines": 5 }),
+            ),
+            ("tail_log", json!({ "path": "/var/log/syslog", "lines": 0 })),
+            (
+                "tail_log",
+                json!({ "path": "/var/log/syslog", "lines": "5" }),
+            ),
+            ("rm_everything", json!({})),
+        ];
+        for (name, value) in rejected {
+            assert!(
+                render(name, &args(value.clone())).is_err(),
+                "{name} {value}"
+            );
+        }
+    }
+}
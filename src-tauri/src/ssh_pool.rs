@@ -1,65 +1,356 @@
 use crate::ssh::SSH;
 use crate::ssh::SSHConfig;
+use crate::ssh_identity::IdentityInfo;
+use crate::{ssh_pty, ssh_tunnel};
 use anyhow::Result;
 use lazy_static::lazy_static;
-use secrecy::SecretString;
+use serde::Serialize;
 use std::collections::HashMap;
-use std::time::Duration;
-use tokio::sync::Mutex;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{Mutex, oneshot, watch};
+
+pub const CONNECTION_STATE_EVENT: &str = "ssh-connection-state";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+const KEEPALIVE_TIMEOUT: Duration = Duration::from_secs(10);
+// Keepalives after a failed one are sent sooner, so a dead link is noticed within a minute.
+const PING_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+const PING_FAILURES_BEFORE_RECONNECT: u32 = 3;
+const RECONNECT_BACKOFF_BASE: Duration = Duration::from_secs(1);
+const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(60);
 
 lazy_static! {
-    static ref CONNECTIONS_BY_ADDRESS: Mutex<HashMap<String, SSH>> = Mutex::new(HashMap::new());
+    static ref CONNECTIONS_BY_ADDRESS: Mutex<HashMap<String, PooledConnection>> =
+        Mutex::new(HashMap::new());
+    static ref STREAMING_COMMANDS_BY_EVENT_KEY: Mutex<HashMap<String, oneshot::Sender<()>>> =
+        Mutex::new(HashMap::new());
+}
+
+pub async fn register_streaming_command(event_key: &str) -> Result<oneshot::Receiver<()>> {
+    let mut commands = STREAMING_COMMANDS_BY_EVENT_KEY.lock().await;
+    if commands.contains_key(event_key) {
+        anyhow::bail!("A streaming command is already running for {event_key}");
+    }
+    let (sender, receiver) = oneshot::channel();
+    commands.insert(event_key.to_string(), sender);
+    Ok(receiver)
+}
+
+pub async fn finish_streaming_command(event_key: &str) {
+    STREAMING_COMMANDS_BY_EVENT_KEY
+        .lock()
+        .await
+        .remove(event_key);
+}
+
+pub async fn cancel_streaming_command(event_key: &str) -> bool {
+    match STREAMING_COMMANDS_BY_EVENT_KEY
+        .lock()
+        .await
+        .remove(event_key)
+    {
+        Some(sender) => sender.send(()).is_ok(),
+        None => false,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConnectionState {
+    Connecting,
+    Ready,
+    Degraded,
+    Closed,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionStatus {
+    pub address: String,
+    pub host: String,
+    /// The key this connection authenticated with.
+    pub identity: IdentityInfo,
+    pub state: ConnectionState,
+    pub latency_ms: Option<u64>,
+    pub last_error: Option<String>,
+    pub last_checked_at: Option<i64>,
+    pub reconnect_attempts: u32,
 }
 
-pub async fn close_connection(address: &str) -> Result<()> {
-    let address = address.to_string();
-    if let Some(ssh) = CONNECTIONS_BY_ADDRESS.lock().await.remove(&address) {
-        ssh.close().await
+impl ConnectionStatus {
+    fn new(address: &str, config: &SSHConfig, state: ConnectionState) -> Self {
+        Self {
+            address: address.to_string(),
+            host: config.host(),
+            identity: config.identity().clone(),
+            state,
+            latency_ms: None,
+            last_error: None,
+            last_checked_at: None,
+            reconnect_attempts: 0,
+        }
+    }
+}
+
+type SharedStatus = Arc<std::sync::Mutex<ConnectionStatus>>;
+
+struct PooledConnection {
+    ssh: SSH,
+    status: SharedStatus,
+    shutdown: watch::Sender<bool>,
+}
+
+impl PooledConnection {
+    /// Starts the keepalive monitor that keeps `status` current and reconnects a dead session.
+    fn start(app: &AppHandle, address: &str, ssh: SSH) -> Self {
+        let status = Arc::new(std::sync::Mutex::new(ConnectionStatus::new(
+            address,
+            &ssh.config,
+            ConnectionState::Ready,
+        )));
+        emit_status(app, &lock_status(&status).clone());
+
+        let (shutdown, shutdown_receiver) = watch::channel(false);
+        tauri::async_runtime::spawn(monitor_connection(
+            app.clone(),
+            ssh.clone(),
+            status.clone(),
+            shutdown_receiver,
+        ));
+        Self {
+            ssh,
+            status,
+            shutdown,
+        }
+    }
+
+    async fn close(self, app: &AppHandle) {
+        let _ = self.shutdown.send(true);
+        self.ssh.close().await;
+        update_status(app, &self.status, |status| {
+            status.state = ConnectionState::Closed;
+            status.latency_ms = None;
+        });
+    }
+}
+
+pub async fn close_connection(app: &AppHandle, address: &str) -> Result<()> {
+    ssh_tunnel::close_tunnels_for_address(address).await;
+    ssh_pty::close_ptys_for_address(address).await;
+    let connection = CONNECTIONS_BY_ADDRESS.lock().await.remove(address);
+    if let Some(connection) = connection {
+        connection.close(app).await;
     }
 
     Ok(())
 }
 
 pub async fn get_connection(address: &str) -> Result<Option<SSH>> {
-    let address = address.to_string();
-    Ok(CONNECTIONS_BY_ADDRESS.lock().await.get(&address).cloned())
-}
-
-pub async fn open_connection(
-    address: &str,
-    host: &str,
-    port: u16,
-    username: String,
-    private_key_openssh: SecretString,
-) -> Result<SSH> {
-    let address = address.to_string();
-    let ssh_config = SSHConfig::new(host, port, username, private_key_openssh)?;
-
-    let timeout_duration = Duration::from_secs(10);
-    if let Some(existing) = CONNECTIONS_BY_ADDRESS.lock().await.get_mut(&address) {
-        if existing.config != ssh_config {
-            log::info!("Closing connection to SSH: {:#}", existing.config.host());
-            existing.close().await;
-
-            log::info!(
-                "Recreating SSH connection to {} with {:?} timeout",
-                ssh_config.host(),
-                timeout_duration
-            );
-            *existing = SSH::connect(&ssh_config, timeout_duration).await?;
+    Ok(CONNECTIONS_BY_ADDRESS
+        .lock()
+        .await
+        .get(address)
+        .map(|connection| connection.ssh.clone()))
+}
+
+pub async fn open_connection(app: &AppHandle, address: &str, ssh_config: SSHConfig) -> Result<SSH> {
+    let stale = {
+        let mut connections = CONNECTIONS_BY_ADDRESS.lock().await;
+        match connections.get(address) {
+            Some(existing) if existing.ssh.config == ssh_config => {
+                return Ok(existing.ssh.clone());
+            }
+            _ => connections.remove(address),
         }
-        return Ok(existing.clone());
+    };
+    if let Some(stale) = stale {
+        log::info!("Closing connection to SSH: {:#}", stale.ssh.config.host());
+        stale.close(app).await;
     }
 
     log::info!(
         "Creating new SSH connection to {} with {:?} timeout",
         ssh_config.host(),
-        timeout_duration
+        CONNECT_TIMEOUT
+    );
+    emit_status(
+        app,
+        &ConnectionStatus::new(address, &ssh_config, ConnectionState::Connecting),
     );
-    let ssh = SSH::connect(&ssh_config, timeout_duration).await?;
-    CONNECTIONS_BY_ADDRESS
+    let ssh = match SSH::connect(&ssh_config, CONNECT_TIMEOUT).await {
+        Ok(ssh) => ssh,
+        Err(e) => {
+            let mut status = ConnectionStatus::new(address, &ssh_config, ConnectionState::Closed);
+            status.last_error = Some(format!("{e:#}"));
+            emit_status(app, &status);
+            return Err(e);
+        }
+    };
+
+    let connection = PooledConnection::start(app, address, ssh.clone());
+    let status = connection.status.clone();
+    let replaced = CONNECTIONS_BY_ADDRESS
         .lock()
         .await
-        .insert(address.clone(), ssh.clone());
+        .insert(address.to_string(), connection);
+    // A concurrent open for the same address may have finished first.
+    if let Some(replaced) = replaced {
+        replaced.close(app).await;
+        emit_status(app, &lock_status(&status).clone());
+    }
     Ok(ssh)
 }
+
+pub async fn pool_status() -> Vec<ConnectionStatus> {
+    let mut statuses = CONNECTIONS_BY_ADDRESS
+        .lock()
+        .await
+        .values()
+        .map(|connection| lock_status(&connection.status).clone())
+        .collect::<Vec<_>>();
+    statuses.sort_by(|a, b| a.address.cmp(&b.address));
+    statuses
+}
+
+async fn monitor_connection(
+    app: AppHandle,
+    ssh: SSH,
+    status: SharedStatus,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let mut ping_failures = 0u32;
+    let mut reconnecting = false;
+    let mut reconnect_attempts = 0u32;
+    loop {
+        let delay = match (reconnecting, ping_failures) {
+            (true, _) => reconnect_backoff(reconnect_attempts + 1),
+            (false, 0) => KEEPALIVE_INTERVAL,
+            (false, _) => PING_RETRY_INTERVAL,
+        };
+        // Closing the connection cancels a pending sleep, keepalive or reconnect.
+        let result = tokio::select! {
+            _ = shutdown.changed() => return,
+            result = async {
+                tokio::time::sleep(delay).await;
+                if reconnecting {
+                    ssh.reconnect().await?;
+                }
+                ssh.ping(KEEPALIVE_TIMEOUT).await
+            } => result,
+        };
+
+        match result {
+            Ok(latency) => {
+                if ping_failures > 0 {
+                    log::info!("SSH connection to {} recovered", ssh.config.host());
+                }
+                ping_failures = 0;
+                reconnecting = false;
+                reconnect_attempts = 0;
+                update_status(&app, &status, |status| {
+                    status.state = ConnectionState::Ready;
+                    status.latency_ms = Some(latency.as_millis() as u64);
+                    status.reconnect_attempts = 0;
+                });
+            }
+            Err(e) => {
+                if reconnecting {
+                    reconnect_attempts = reconnect_attempts.saturating_add(1);
+                } else {
+                    ping_failures = ping_failures.saturating_add(1);
+                    reconnecting = should_reconnect(ping_failures, ssh.is_closed().await);
+                }
+                log::warn!(
+                    "SSH connection to {} is unhealthy ({ping_failures} failed keepalives, \
+                     {reconnect_attempts} reconnect attempts): {e:#}",
+                    ssh.config.host()
+                );
+                update_status(&app, &status, |status| {
+                    status.state = match reconnecting {
+                        true => ConnectionState::Connecting,
+                        false => ConnectionState::Degraded,
+                    };
+                    status.latency_ms = None;
+                    status.last_error = Some(format!("{e:#}"));
+                    status.reconnect_attempts = reconnect_attempts;
+                });
+            }
+        }
+    }
+}
+
+/// A single lost keepalive on a busy link is not worth tearing down the session and every
+/// channel on it; reconnect once the transport is gone or keepalives keep failing.
+fn should_reconnect(ping_failures: u32, transport_closed: bool) -> bool {
+    transport_closed || ping_failures >= PING_FAILURES_BEFORE_RECONNECT
+}
+
+/// Exponential backoff before the given reconnect attempt, starting at one second.
+fn reconnect_backoff(attempt: u32) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(16);
+    RECONNECT_BACKOFF_BASE
+        .saturating_mul(1 << exponent)
+        .min(RECONNECT_BACKOFF_MAX)
+}
+
+/// Applies `update` and emits the status when its state changed.
+fn update_status(
+    app: &AppHandle,
+    status: &SharedStatus,
+    update: impl FnOnce(&mut ConnectionStatus),
+) {
+    let (changed, snapshot) = {
+        let mut status = lock_status(status);
+        let previous_state = status.state;
+        update(&mut status);
+        status.last_checked_at = Some(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs() as i64,
+        );
+        (status.state != previous_state, status.clone())
+    };
+    if changed {
+        emit_status(app, &snapshot);
+    }
+}
+
+fn emit_status(app: &AppHandle, status: &ConnectionStatus) {
+    if let Err(e) = app.emit(CONNECTION_STATE_EVENT, status) {
+        log::warn!("Failed to emit SSH connection state: {e}");
+    }
+}
+
+fn lock_status(status: &SharedStatus) -> std::sync::MutexGuard<'_, ConnectionStatus> {
+    status
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reconnect_backoff_doubles_up_to_the_cap() {
+        let delays = (1..=8).map(reconnect_backoff).collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            [1, 2, 4, 8, 16, 32, 60, 60]
+                .map(Duration::from_secs)
+                .to_vec()
+        );
+        assert_eq!(reconnect_backoff(u32::MAX), RECONNECT_BACKOFF_MAX);
+    }
+
+    #[test]
+    fn reconnects_only_after_repeated_keepalive_failures_or_a_closed_transport() {
+        assert!(!should_reconnect(1, false));
+        assert!(!should_reconnect(PING_FAILURES_BEFORE_RECONNECT - 1, false));
+        assert!(should_reconnect(PING_FAILURES_BEFORE_RECONNECT, false));
+        assert!(should_reconnect(1, true));
+    }
+}
@@ -0,0 +1,426 @@
+use crate::ssh::{CommandOptions, SSH};
+use crate::ssh_pool;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+const PROBE_MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+const SECTION_MARKER: &str = "__ARGON_PROBE__:";
+// Every probe tolerates missing tools; an absent section just leaves that part of the inventory empty.
+const PROBE_SCRIPT: &str = "\
+echo __ARGON_PROBE__:os_release; cat /etc/os-release 2>/dev/null; \
+echo __ARGON_PROBE__:kernel; uname -srm 2>/dev/null; \
+echo __ARGON_PROBE__:cpu_count; nproc 2>/dev/null; \
+echo __ARGON_PROBE__:cpuinfo; grep -m1 '^model name' /proc/cpuinfo 2>/dev/null; \
+echo __ARGON_PROBE__:meminfo; cat /proc/meminfo 2>/dev/null; \
+echo __ARGON_PROBE__:disks; df -P -k -x tmpfs -x devtmpfs -x overlay -x squashfs 2>/dev/null; \
+echo __ARGON_PROBE__:docker_version; docker version --format '{{.Server.Version}}' 2>/dev/null; \
+echo __ARGON_PROBE__:containers; docker ps --format '{{.ID}}\t{{.Names}}\t{{.Image}}\t{{.Status}}' 2>/dev/null; \
+echo __ARGON_PROBE__:services; systemctl list-units --type=service --all --no-legend --no-pager --plain 2>/dev/null; \
+echo __ARGON_PROBE__:ports; ss -H -ltnup 2>/dev/null; \
+echo __ARGON_PROBE__:uptime; cat /proc/uptime 2>/dev/null; \
+true";
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerInventory {
+    pub os: Option<OsRelease>,
+    pub kernel: Option<String>,
+    pub cpu: CpuInfo,
+    pub memory: Option<MemoryInfo>,
+    pub disks: Vec<DiskUsage>,
+    pub docker: Option<DockerInfo>,
+    pub services: Vec<ServiceUnit>,
+    pub listening_ports: Vec<ListeningPort>,
+    /// Server clock minus local clock; positive when the server is ahead.
+    pub clock_skew_ms: Option<i64>,
+    pub uptime_secs: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OsRelease {
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub version_id: Option<String>,
+    pub pretty_name: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CpuInfo {
+    pub count: Option<u32>,
+    pub model: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryInfo {
+    pub total_bytes: u64,
+    pub available_bytes: Option<u64>,
+    pub swap_total_bytes: Option<u64>,
+    pub swap_free_bytes: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskUsage {
+    pub filesystem: String,
+    pub mount: String,
+    pub total_bytes: u64,
+    pub used_bytes: u64,
+    pub available_bytes: u64,
+    pub used_percent: Option<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DockerInfo {
+    pub version: String,
+    pub containers: Vec<ContainerInfo>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerInfo {
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    pub status: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceUnit {
+    pub unit: String,
+    pub load: String,
+    pub active: String,
+    pub sub: String,
+    pub description: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListeningPort {
+    pub protocol: String,
+    pub address: String,
+    pub port: u16,
+    pub process: Option<String>,
+}
+
+pub async fn probe_server(ssh: &SSH) -> Result<ServerInventory> {
+    let output = ssh
+        .run_command_with_options(
+            PROBE_SCRIPT,
+            CommandOptions {
+                timeout: Some(PROBE_TIMEOUT),
+                max_output_bytes: Some(PROBE_MAX_OUTPUT_BYTES),
+            },
+        )
+        .await?;
+    output.exit_code()?;
+    let mut inventory = parse_inventory(&output.stdout);
+    inventory.clock_skew_ms = match measure_clock_skew(ssh).await {
+        Ok(skew) => Some(skew),
+        Err(e) => {
+            log::warn!("Could not measure the server clock skew: {e:#}");
+            None
+        }
+    };
+    Ok(inventory)
+}
+
+/// Compares the server clock against the midpoint of the local send and receive times, which
+/// cancels out the round trip when it is symmetric.
+async fn measure_clock_skew(ssh: &SSH) -> Result<i64> {
+    let sent_at = unix_millis();
+    let (output, code) = ssh.run_command("date +%s%3N").await?;
+    let received_at = unix_millis();
+    if code != 0 {
+        anyhow::bail!("date exited with {code}");
+    }
+    let remote_millis = output.trim().parse::<i64>()?;
+    Ok(remote_millis - (sent_at + received_at) / 2)
+}
+
+fn unix_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+pub fn parse_inventory(output: &str) -> ServerInventory {
+    let sections = split_sections(output);
+    let section = |name: &str| sections.get(name).copied().unwrap_or_default();
+    let first_line = |name: &str| {
+        section(name)
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .map(str::to_string)
+    };
+
+    ServerInventory {
+        os: parse_os_release(section("os_release")),
+        kernel: first_line("kernel"),
+        cpu: CpuInfo {
+            count: first_line("cpu_count").and_then(|count| count.parse().ok()),
+            model: first_line("cpuinfo")
+                .and_then(|line| Some(line.split_once(':')?.1.trim().to_string())),
+        },
+        memory: parse_meminfo(section("meminfo")),
+        disks: parse_df(section("disks")),
+        docker: first_line("docker_version").map(|version| DockerInfo {
+            version,
+            containers: parse_containers(section("containers")),
+        }),
+        services: parse_services(section("services")),
+        listening_ports: parse_listening_ports(section("ports")),
+        clock_skew_ms: None,
+        uptime_secs: first_line("uptime")
+            .and_then(|line| line.split_whitespace().next()?.parse::<f64>().ok())
+            .map(|secs| secs as u64),
+    }
+}
+
+fn split_sections(output: &str) -> HashMap<&str, &str> {
+    let mut sections = HashMap::new();
+    let mut rest = output;
+    while let Some(start) = rest.find(SECTION_MARKER) {
+        let after_marker = &rest[start + SECTION_MARKER.len()..];
+        let (name, body) = after_marker.split_once('\n').unwrap_or((after_marker, ""));
+        let end = body.find(SECTION_MARKER).unwrap_or(body.len());
+        sections.insert(name.trim(), &body[..end]);
+        rest = &body[end..];
+    }
+    sections
+}
+
+fn parse_os_release(section: &str) -> Option<OsRelease> {
+    let mut release = OsRelease::default();
+    for line in section.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let value = Some(value.trim_matches(['"', '\'']).to_string());
+        match key {
+            "ID" => release.id = value,
+            "NAME" => release.name = value,
+            "VERSION_ID" => release.version_id = value,
+            "PRETTY_NAME" => release.pretty_name = value,
+            _ => {}
+        }
+    }
+    (release != OsRelease::default()).then_some(release)
+}
+
+fn parse_meminfo(section: &str) -> Option<MemoryInfo> {
+    let fields = section
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            let kib = value.split_whitespace().next()?.parse::<u64>().ok()?;
+            Some((key.trim(), kib * 1024))
+        })
+        .collect::<HashMap<_, _>>();
+    Some(MemoryInfo {
+        total_bytes: *fields.get("MemTotal")?,
+        available_bytes: fields.get("MemAvailable").copied(),
+        swap_total_bytes: fields.get("SwapTotal").copied(),
+        swap_free_bytes: fields.get("SwapFree").copied(),
+    })
+}
+
+/// `df -P -k` output; mount points may contain spaces, so everything after the capacity is the mount.
+fn parse_df(section: &str) -> Vec<DiskUsage> {
+    section
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.len() < 6 {
+                return None;
+            }
+            let kib = |field: &str| field.parse::<u64>().ok().map(|kib| kib * 1024);
+            Some(DiskUsage {
+                filesystem: fields[0].to_string(),
+                total_bytes: kib(fields[1])?,
+                used_bytes: kib(fields[2])?,
+                available_bytes: kib(fields[3])?,
+                used_percent: fields[4].trim_end_matches('%').parse().ok(),
+                mount: fields[5..].join(" "),
+            })
+        })
+        .collect()
+}
+
+fn parse_containers(section: &str) -> Vec<ContainerInfo> {
+    section
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, '\t');
+            Some(ContainerInfo {
+                id: fields.next()?.trim().to_string(),
+                name: fields.next()?.to_string(),
+                image: fields.next()?.to_string(),
+                status: fields.next()?.trim_end().to_string(),
+            })
+        })
+        .filter(|container| !container.id.is_empty())
+        .collect()
+}
+
+fn parse_services(section: &str) -> Vec<ServiceUnit> {
+    section
+        .lines()
+        .filter_map(|line| {
+            // Older systemd prints a status bullet in front of failed units even with `--plain`.
+            let line = line.trim_start_matches(['●', '*', ' ']);
+            let mut fields = line.split_whitespace();
+            let unit = fields.next()?;
+            if !unit.ends_with(".service") {
+                return None;
+            }
+            Some(ServiceUnit {
+                unit: unit.to_string(),
+                load: fields.next()?.to_string(),
+                active: fields.next()?.to_string(),
+                sub: fields.next()?.to_string(),
+                description: fields.collect::<Vec<_>>().join(" "),
+            })
+        })
+        .collect()
+}
+
+/// `ss -H -ltnup` lines: netid, state, queues, local address, peer address and the process when
+/// it is visible to the probing user.
+fn parse_listening_ports(section: &str) -> Vec<ListeningPort> {
+    let mut ports = section
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.len() < 5 {
+                return None;
+            }
+            let (address, port) = fields[4].rsplit_once(':')?;
+            let address = address.trim_start_matches('[').trim_end_matches(']');
+            // Link-local listeners carry the interface, e.g. `127.0.0.53%lo`.
+            let address = address.split('%').next().unwrap_or(address);
+            let process = fields.get(6).and_then(|process| {
+                let name = process.split('"').nth(1)?;
+                Some(name.to_string())
+            });
+            Some(ListeningPort {
+                protocol: fields[0].to_string(),
+                address: address.to_string(),
+                port: port.parse().ok()?,
+                process,
+            })
+        })
+        .collect::<Vec<_>>();
+    ports.sort_by(|a, b| (a.port, &a.protocol, &a.address).cmp(&(b.port, &b.protocol, &b.address)));
+    ports.dedup();
+    ports
+}
+
+#[tauri::command]
+pub async fn ssh_probe_server(address: &str) -> Result<ServerInventory, String> {
+    let ssh = ssh_pool::get_connection(address)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or("No SSH connection")?;
+    probe_server(&ssh).await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UBUNTU_DOCKER: &str = include_str!("../tests/fixtures/ssh_probe/ubuntu-docker.txt");
+    const MINIMAL_ALPINE: &str = include_str!("../tests/fixtures/ssh_probe/minimal-alpine.txt");
+
+    #[test]
+    fn parses_full_inventory_fixture() {
+        let inventory = parse_inventory(UBUNTU_DOCKER);
+
+        let os = inventory.os.expect("os release should parse");
+        assert_eq!(os.id.as_deref(), Some("ubuntu"));
+        assert_eq!(os.version_id.as_deref(), Some("24.04"));
+        assert_eq!(os.pretty_name.as_deref(), Some("Ubuntu 24.04.1 LTS"));
+        assert_eq!(
+            inventory.kernel.as_deref(),
+            Some("Linux 6.8.0-45-generic x86_64")
+        );
+        assert_eq!(inventory.cpu.count, Some(8));
+        assert_eq!(
+            inventory.cpu.model.as_deref(),
+            Some("AMD EPYC 7543P 32-Core Processor")
+        );
+
+        let memory = inventory.memory.expect("meminfo should parse");
+        assert_eq!(memory.total_bytes, 32_853_340 * 1024);
+        assert_eq!(memory.available_bytes, Some(27_201_520 * 1024));
+
+        assert_eq!(inventory.disks.len(), 3);
+        assert_eq!(inventory.disks[0].mount, "/");
+        assert_eq!(inventory.disks[0].used_percent, Some(42));
+        assert_eq!(inventory.disks[2].mount, "/mnt/argon data");
+
+        let docker = inventory.docker.expect("docker should parse");
+        assert_eq!(docker.version, "27.3.1");
+        assert_eq!(docker.containers.len(), 2);
+        assert_eq!(docker.containers[1].name, "argon-bitcoin");
+        assert_eq!(docker.containers[1].status, "Up 3 days (healthy)");
+
+        assert_eq!(inventory.services.len(), 4);
+        let failed = inventory
+            .services
+            .iter()
+            .find(|unit| unit.active == "failed")
+            .expect("failed unit should parse");
+        assert_eq!(failed.unit, "argon-backup.service");
+        assert_eq!(failed.description, "Argon nightly backup");
+
+        assert_eq!(
+            inventory
+                .listening_ports
+                .iter()
+                .map(|port| (port.protocol.as_str(), port.address.as_str(), port.port))
+                .collect::<Vec<_>>(),
+            [
+                ("tcp", "0.0.0.0", 22),
+                ("tcp", "::", 22),
+                ("tcp", "127.0.0.53", 53),
+                ("udp", "127.0.0.53", 53),
+                ("tcp", "0.0.0.0", 9944),
+            ]
+        );
+        assert_eq!(
+            inventory.listening_ports[0].process.as_deref(),
+            Some("sshd")
+        );
+        assert_eq!(inventory.uptime_secs, Some(273_456));
+    }
+
+    #[test]
+    fn tolerates_missing_tools() {
+        let inventory = parse_inventory(MINIMAL_ALPINE);
+
+        assert_eq!(inventory.os.and_then(|os| os.id).as_deref(), Some("alpine"));
+        assert_eq!(inventory.cpu.count, Some(1));
+        assert_eq!(inventory.cpu.model, None);
+        assert_eq!(inventory.docker, None);
+        assert!(inventory.services.is_empty());
+        assert!(inventory.listening_ports.is_empty());
+        assert_eq!(inventory.disks.len(), 1);
+        assert_eq!(
+            inventory.memory.map(|memory| memory.swap_total_bytes),
+            Some(Some(0))
+        );
+        assert_eq!(inventory.uptime_secs, Some(59));
+
+        assert_eq!(parse_inventory(""), ServerInventory::default());
+    }
+}
@@ -0,0 +1,211 @@
+use crate::ssh::Utf8ChunkDecoder;
+use crate::{ssh_audit, ssh_operations, ssh_pool};
+use anyhow::Result;
+use lazy_static::lazy_static;
+use russh::client::Msg;
+use russh::{ChannelMsg, ChannelReadHalf, ChannelWriteHalf};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Mutex;
+
+pub const PTY_OUTPUT_EVENT: &str = "ssh-pty-output";
+pub const PTY_CLOSED_EVENT: &str = "ssh-pty-closed";
+// Stands in for the command in the audit log; what was typed is not recorded.
+const PTY_AUDIT_COMMAND: &str = "<interactive shell>";
+
+lazy_static! {
+    static ref PTY_SESSIONS_BY_ID: Mutex<HashMap<String, PtySession>> = Mutex::new(HashMap::new());
+}
+
+struct PtySession {
+    address: String,
+    writer: Arc<ChannelWriteHalf<Msg>>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PtyOutput {
+    pub session_id: String,
+    pub data: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PtyClosed {
+    pub session_id: String,
+    pub exit_status: Option<u32>,
+}
+
+/// Starts a shell over the pooled connection for `address` and streams its output as
+/// `ssh-pty-output` events until it exits, which is reported as `ssh-pty-closed`.
+pub async fn open_pty(app: &AppHandle, address: &str, cols: u32, rows: u32) -> Result<String> {
+    let ssh = ssh_pool::get_connection(address)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("No SSH connection"))?;
+    let (reader, writer) = ssh.open_pty(cols, rows).await?.split();
+
+    let session_id = format!("{:016x}", rand::random::<u64>());
+    log::info!("Opened SSH shell {session_id} on {address}");
+    PTY_SESSIONS_BY_ID.lock().await.insert(
+        session_id.clone(),
+        PtySession {
+            address: address.to_string(),
+            writer: Arc::new(writer),
+        },
+    );
+    tauri::async_runtime::spawn(forward_output(
+        app.clone(),
+        address.to_string(),
+        session_id.clone(),
+        reader,
+    ));
+    Ok(session_id)
+}
+
+pub async fn write_pty(session_id: &str, data: &[u8]) -> Result<()> {
+    // Write outside the registry lock; a full channel window must not stall other sessions.
+    let writer = session_writer(session_id).await?;
+    writer.data(data).await?;
+    Ok(())
+}
+
+pub async fn resize_pty(session_id: &str, cols: u32, rows: u32) -> Result<()> {
+    let writer = session_writer(session_id).await?;
+    writer.window_change(cols, rows, 0, 0).await?;
+    Ok(())
+}
+
+pub async fn close_pty(session_id: &str) -> bool {
+    let session = PTY_SESSIONS_BY_ID.lock().await.remove(session_id);
+    match session {
+        Some(session) => {
+            log::info!("Closing SSH shell {session_id} on {}", session.address);
+            let _ = session.writer.eof().await;
+            let _ = session.writer.close().await;
+            true
+        }
+        None => false,
+    }
+}
+
+pub async fn close_ptys_for_address(address: &str) {
+    let sessions = {
+        let mut sessions = PTY_SESSIONS_BY_ID.lock().await;
+        let ids = sessions
+            .iter()
+            .filter(|(_, session)| session.address == address)
+            .map(|(id, _)| id.clone())
+            .collect::<Vec<_>>();
+        ids.into_iter()
+            .filter_map(|id| sessions.remove(&id))
+            .collect::<Vec<_>>()
+    };
+    for session in sessions {
+        let _ = session.writer.close().await;
+    }
+}
+
+async fn session_writer(session_id: &str) -> Result<Arc<ChannelWriteHalf<Msg>>> {
+    PTY_SESSIONS_BY_ID
+        .lock()
+        .await
+        .get(session_id)
+        .map(|session| session.writer.clone())
+        .ok_or_else(|| anyhow::anyhow!("No SSH shell session {session_id}"))
+}
+
+async fn forward_output(
+    app: AppHandle,
+    address: String,
+    session_id: String,
+    mut reader: ChannelReadHalf,
+) {
+    let started_at = Instant::now();
+    let mut decoder = Utf8ChunkDecoder::default();
+    let mut output_bytes = 0;
+    let mut exit_status = None;
+    let emit = |data: String| {
+        if data.is_empty() {
+            return;
+        }
+        let output = PtyOutput {
+            session_id: session_id.clone(),
+            data,
+        };
+        if let Err(e) = app.emit(PTY_OUTPUT_EVENT, output) {
+            log::warn!("Failed to emit SSH shell output: {e}");
+        }
+    };
+
+    while let Some(msg) = reader.wait().await {
+        match msg {
+            // A PTY merges stderr into stdout, but some servers still send it separately.
+            ChannelMsg::Data { ref data } | ChannelMsg::ExtendedData { ref data, .. } => {
+                output_bytes += data.len();
+                emit(decoder.decode(data));
+            }
+            ChannelMsg::ExitStatus {
+                exit_status: status,
+            } => exit_status = Some(status),
+            _ => {}
+        }
+    }
+    emit(decoder.finish());
+
+    PTY_SESSIONS_BY_ID.lock().await.remove(&session_id);
+    log::info!("SSH shell {session_id} on {address} ended");
+    ssh_audit::AuditLog::for_instance(&app).record(
+        &address,
+        PTY_AUDIT_COMMAND,
+        Some("pty"),
+        started_at,
+        // A shell closed by the server or the user often ends without an exit status.
+        exit_status
+            .map(|status| (status, output_bytes))
+            .ok_or("SSHShellEndedWithoutExitStatus"),
+    );
+    let closed = PtyClosed {
+        session_id,
+        exit_status,
+    };
+    if let Err(e) = app.emit(PTY_CLOSED_EVENT, closed) {
+        log::warn!("Failed to emit SSH shell close: {e}");
+    }
+}
+
+/// Returns the session id used by the other `ssh_*_pty` commands and in events.
+/// Refused in strict mode, since a shell runs anything that is typed into it.
+#[tauri::command]
+pub async fn ssh_open_pty(
+    app: AppHandle,
+    address: &str,
+    cols: u32,
+    rows: u32,
+) -> Result<String, String> {
+    ssh_operations::ensure_raw_commands_allowed()?;
+    open_pty(&app, address, cols, rows)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn ssh_write_pty(session_id: &str, data: String) -> Result<(), String> {
+    write_pty(session_id, data.as_bytes())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn ssh_resize_pty(session_id: &str, cols: u32, rows: u32) -> Result<(), String> {
+    resize_pty(session_id, cols, rows)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn ssh_close_pty(session_id: &str) -> Result<bool, String> {
+    Ok(close_pty(session_id).await)
+}
@@ -0,0 +1,503 @@
+use crate::ssh_compression::Compression;
+use anyhow::Result;
+use russh::Channel;
+use russh::ChannelMsg;
+use russh::client::Msg;
+use russh_sftp::client::SftpSession;
+use russh_sftp::protocol::{FileAttributes, OpenFlags};
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::io::SeekFrom;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt};
+
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
+// Smaller parts gain little from a separate session and cost an extra handshake each.
+const PARALLEL_MIN_PART_SIZE: u64 = 64 * 1024 * 1024;
+pub const REMOTE_PARTIAL_SUFFIX: &str = ".argon-partial";
+pub const LOCAL_PARTIAL_SUFFIX: &str = ".partial";
+
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TransferOptions {
+    /// Continue from an existing partial file left behind by an interrupted transfer.
+    pub resume: bool,
+    /// Compare a local SHA-256 against `sha256sum` on the server before committing the file.
+    pub verify_sha256: bool,
+    pub mode: Option<u32>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    /// Modification time (unix seconds) to stamp on the uploaded file.
+    pub mtime: Option<u32>,
+    /// Caps upload throughput in bytes per second, shared by all parts of a parallel upload.
+    pub max_bytes_per_sec: Option<u64>,
+    /// Splits large uploads into up to this many parts, sent over separate transfer sessions
+    /// and joined on the server.
+    pub parallel_parts: usize,
+    /// Compresses `cat` transfers on the fly when the server has a matching decompressor.
+    /// Compressed transfers always start over and are sent in one piece.
+    pub compression: Compression,
+}
+
+impl Default for TransferOptions {
+    fn default() -> Self {
+        Self {
+            resume: false,
+            verify_sha256: false,
+            mode: None,
+            uid: None,
+            gid: None,
+            mtime: None,
+            max_bytes_per_sec: None,
+            parallel_parts: 1,
+            compression: Compression::None,
+        }
+    }
+}
+
+impl TransferOptions {
+    /// For large files, where resuming and a checksum are worth the extra round trips.
+    pub fn large_file() -> Self {
+        Self {
+            resume: true,
+            verify_sha256: true,
+            ..Self::default()
+        }
+    }
+}
+
+/// Paces writes to a byte rate. Each caller reserves the time its chunk takes at that rate, so
+/// concurrent parts share the budget instead of each getting the full rate.
+pub struct RateLimiter {
+    bytes_per_sec: u64,
+    next_send_at: std::sync::Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec: bytes_per_sec.max(1),
+            next_send_at: std::sync::Mutex::new(None),
+        }
+    }
+
+    pub fn for_options(options: &TransferOptions) -> Option<Arc<Self>> {
+        options
+            .max_bytes_per_sec
+            .filter(|rate| *rate > 0)
+            .map(|rate| Arc::new(Self::new(rate)))
+    }
+
+    /// Waits until `bytes` more may be sent.
+    pub async fn acquire(&self, bytes: usize) {
+        let wait = self.reserve(Instant::now(), bytes);
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn reserve(&self, now: Instant, bytes: usize) -> Duration {
+        let mut next_send_at = self
+            .next_send_at
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        // Idle time is not banked, so a paused transfer cannot burst above the rate afterwards.
+        let start = next_send_at.map_or(now, |next| next.max(now));
+        *next_send_at =
+            Some(start + Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64));
+        start - now
+    }
+}
+
+/// Tracks when a transfer last moved, so it can fail on a stall instead of after a fixed time.
+pub struct StallWatchdog {
+    last_progress: std::sync::Mutex<(Instant, u64, bool)>,
+}
+
+impl Default for StallWatchdog {
+    fn default() -> Self {
+        Self {
+            last_progress: std::sync::Mutex::new((Instant::now(), 0, false)),
+        }
+    }
+}
+
+impl StallWatchdog {
+    pub fn record(&self, transferred: u64, total: u64) {
+        let mut last_progress = self
+            .last_progress
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if transferred != last_progress.1 {
+            *last_progress = (
+                Instant::now(),
+                transferred,
+                total > 0 && transferred >= total,
+            );
+        }
+    }
+
+    /// Time since the last progress, or `None` once every byte was sent. Verifying and
+    /// committing the file afterwards is bounded by the commands' own timeouts.
+    fn idle_for(&self) -> Option<Duration> {
+        let (at, _, done) = *self
+            .last_progress
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        (!done).then(|| at.elapsed())
+    }
+}
+
+/// Runs `transfer` until it finishes, failing once it reports no progress for `stall_timeout`.
+pub async fn with_stall_timeout<T>(
+    stall_timeout: Duration,
+    watchdog: &StallWatchdog,
+    transfer: impl Future<Output = Result<T>>,
+) -> Result<T> {
+    tokio::pin!(transfer);
+    loop {
+        let remaining = watchdog
+            .idle_for()
+            .map_or(stall_timeout, |idle| stall_timeout.saturating_sub(idle));
+        if remaining.is_zero() {
+            // The frontend retries uploads on this wording.
+            anyhow::bail!("SSH upload timed out after {stall_timeout:?} without progress");
+        }
+        tokio::select! {
+            result = &mut transfer => return result,
+            _ = tokio::time::sleep(remaining) => {}
+        }
+    }
+}
+
+/// Byte ranges for a parallel upload. Files too small to benefit are sent as one part.
+pub fn plan_parts(total: u64, max_parts: usize) -> Vec<Range<u64>> {
+    let parts = (total / PARALLEL_MIN_PART_SIZE).clamp(1, max_parts.max(1) as u64);
+    let part_size = total.div_ceil(parts).max(1);
+    (0..parts)
+        .map(|part| part * part_size..((part + 1) * part_size).min(total))
+        .collect()
+}
+
+/// Starts the SFTP subsystem on a fresh channel. Returns `None` when the server refuses it,
+/// so callers can fall back to streaming through `cat`.
+pub async fn open_session(mut channel: Channel<Msg>) -> Result<Option<SftpSession>> {
+    channel.request_subsystem(true, "sftp").await?;
+    loop {
+        match channel.wait().await {
+            Some(ChannelMsg::Success) => break,
+            Some(ChannelMsg::Failure) | None => return Ok(None),
+            Some(_) => {}
+        }
+    }
+    Ok(Some(SftpSession::new(channel.into_stream()).await?))
+}
+
+/// SFTP paths are relative to the login directory and do not expand `~`.
+/// Returns `None` for `~user/` paths, which only the shell can resolve.
+pub fn remote_path(path: &str) -> Option<String> {
+    match path.strip_prefix('~') {
+        None => Some(path.to_string()),
+        Some("") => Some(".".to_string()),
+        Some(rest) => rest.strip_prefix('/').map(|rest| rest.to_string()),
+    }
+}
+
+pub async fn remote_size(sftp: &SftpSession, path: &str) -> Option<u64> {
+    sftp.metadata(path)
+        .await
+        .ok()
+        .and_then(|metadata| metadata.size)
+}
+
+/// Writes `source` to `remote_path` starting at `offset`, hashing the whole source including
+/// the part that already exists remotely. Returns the hex SHA-256 of the source.
+pub async fn upload_from<R>(
+    sftp: &SftpSession,
+    source: &mut R,
+    remote_path: &str,
+    offset: u64,
+    total: u64,
+    throttle: Option<&RateLimiter>,
+    on_progress: &mut impl FnMut(u64, u64) -> Result<()>,
+) -> Result<String>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let mut hasher = Sha256::new();
+    source.seek(SeekFrom::Start(0)).await?;
+    hash_prefix(source, offset, &mut hasher).await?;
+
+    let mut flags = OpenFlags::WRITE | OpenFlags::CREATE;
+    if offset == 0 {
+        flags |= OpenFlags::TRUNCATE;
+    }
+    let mut file = sftp.open_with_flags(remote_path, flags).await?;
+    file.seek(SeekFrom::Start(offset)).await?;
+
+    copy_with_progress(
+        source,
+        &mut file,
+        offset,
+        total,
+        Some(&mut hasher),
+        throttle,
+        on_progress,
+    )
+    .await?;
+    file.shutdown().await?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Writes `range` of `source` to its own remote file, continuing after whatever that file
+/// already holds when `resume` is set. Progress counts bytes of the part.
+pub async fn upload_part<R>(
+    sftp: &SftpSession,
+    source: &mut R,
+    remote_path: &str,
+    range: Range<u64>,
+    resume: bool,
+    throttle: Option<&RateLimiter>,
+    on_progress: &mut impl FnMut(u64, u64) -> Result<()>,
+) -> Result<()>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let len = range.end - range.start;
+    let offset = match resume {
+        true => remote_size(sftp, remote_path)
+            .await
+            .filter(|size| *size <= len)
+            .unwrap_or(0),
+        false => 0,
+    };
+
+    let mut flags = OpenFlags::WRITE | OpenFlags::CREATE;
+    if offset == 0 {
+        flags |= OpenFlags::TRUNCATE;
+    }
+    let mut file = sftp.open_with_flags(remote_path, flags).await?;
+    file.seek(SeekFrom::Start(offset)).await?;
+    source.seek(SeekFrom::Start(range.start + offset)).await?;
+
+    let mut part = source.take(len - offset);
+    copy_with_progress(
+        &mut part,
+        &mut file,
+        offset,
+        len,
+        None,
+        throttle,
+        on_progress,
+    )
+    .await?;
+    file.shutdown().await?;
+    Ok(())
+}
+
+/// Hex SHA-256 of the first `len` bytes of `reader`.
+pub async fn sha256_of<R>(reader: &mut R, len: u64) -> Result<String>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let mut hasher = Sha256::new();
+    reader.seek(SeekFrom::Start(0)).await?;
+    hash_prefix(reader, len, &mut hasher).await?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Appends `remote_path` from `offset` onto `destination`, which must already hold the first
+/// `offset` bytes. Returns the hex SHA-256 of the complete local file.
+pub async fn download_into<W>(
+    sftp: &SftpSession,
+    remote_path: &str,
+    destination: &mut W,
+    offset: u64,
+    total: u64,
+    on_progress: &mut impl FnMut(u64, u64) -> Result<()>,
+) -> Result<String>
+where
+    W: AsyncRead + AsyncWrite + AsyncSeek + Unpin,
+{
+    let mut hasher = Sha256::new();
+    destination.seek(SeekFrom::Start(0)).await?;
+    hash_prefix(destination, offset, &mut hasher).await?;
+
+    let mut file = sftp.open_with_flags(remote_path, OpenFlags::READ).await?;
+    file.seek(SeekFrom::Start(offset)).await?;
+
+    copy_with_progress(
+        &mut file,
+        destination,
+        offset,
+        total,
+        Some(&mut hasher),
+        None,
+        on_progress,
+    )
+    .await?;
+    destination.flush().await?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Gives the partial file the requested attributes before it replaces `remote_path`. A mode or
+/// owner that was not requested is copied from the file being replaced, so an update does not
+/// reset its permissions.
+pub async fn apply_upload_attributes(
+    sftp: &SftpSession,
+    partial_path: &str,
+    remote_path: &str,
+    options: &TransferOptions,
+) -> Result<()> {
+    let existing = sftp.metadata(remote_path).await.ok();
+    let sets_owner = options.uid.is_some() || options.gid.is_some();
+    let mode = options.mode.or_else(|| {
+        existing
+            .as_ref()
+            .and_then(|metadata| metadata.permissions)
+            .map(|permissions| permissions & 0o7777)
+    });
+    if mode.is_some() || options.mtime.is_some() || sets_owner {
+        let mut attributes = FileAttributes::empty();
+        attributes.permissions = mode;
+        // SFTP sets access and modification times together.
+        attributes.atime = options.mtime;
+        attributes.mtime = options.mtime;
+        if sets_owner {
+            // SFTP sets uid and gid together, so keep whichever side was not requested.
+            let current = sftp.metadata(partial_path).await?;
+            attributes.uid = options.uid.or(current.uid);
+            attributes.gid = options.gid.or(current.gid);
+        }
+        sftp.set_metadata(partial_path, attributes).await?;
+    }
+
+    if let Some(existing) = existing.filter(|_| !sets_owner) {
+        let current = sftp.metadata(partial_path).await?;
+        if existing.uid != current.uid || existing.gid != current.gid {
+            let mut owner = FileAttributes::empty();
+            owner.uid = existing.uid;
+            owner.gid = existing.gid;
+            // Only root can give a file away; other logins keep their own ownership.
+            if let Err(e) = sftp.set_metadata(partial_path, owner).await {
+                log::warn!("Could not keep the owner of {remote_path}: {e}");
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn parse_sha256sum_output(output: &str) -> Option<String> {
+    let hash = output.split_whitespace().next()?.to_ascii_lowercase();
+    (hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit())).then_some(hash)
+}
+
+async fn hash_prefix<R>(reader: &mut R, len: u64, hasher: &mut Sha256) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut buffer = vec![0u8; COPY_BUFFER_SIZE];
+    let mut remaining = len;
+    while remaining > 0 {
+        let want = remaining.min(buffer.len() as u64) as usize;
+        let n = reader.read(&mut buffer[..want]).await?;
+        if n == 0 {
+            anyhow::bail!("Partial transfer is shorter than expected");
+        }
+        hasher.update(&buffer[..n]);
+        remaining -= n as u64;
+    }
+    Ok(())
+}
+
+async fn copy_with_progress<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    offset: u64,
+    total: u64,
+    mut hasher: Option<&mut Sha256>,
+    throttle: Option<&RateLimiter>,
+    on_progress: &mut impl FnMut(u64, u64) -> Result<()>,
+) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buffer = vec![0u8; COPY_BUFFER_SIZE];
+    let mut transferred = offset;
+    on_progress(transferred, total)?;
+    loop {
+        let n = reader.read(&mut buffer).await?;
+        if n == 0 {
+            break;
+        }
+        if let Some(throttle) = throttle {
+            throttle.acquire(n).await;
+        }
+        writer.write_all(&buffer[..n]).await?;
+        if let Some(hasher) = hasher.as_deref_mut() {
+            hasher.update(&buffer[..n]);
+        }
+        transferred += n as u64;
+        on_progress(transferred, total)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_home_relative_paths_for_sftp() {
+        assert_eq!(remote_path("/etc/argon").as_deref(), Some("/etc/argon"));
+        assert_eq!(remote_path("~/config.json").as_deref(), Some("config.json"));
+        assert_eq!(remote_path("~").as_deref(), Some("."));
+        assert_eq!(remote_path("~root/config.json"), None);
+    }
+
+    #[test]
+    fn paces_reservations_to_the_byte_rate() {
+        let limiter = RateLimiter::new(1024);
+        let start = Instant::now();
+        assert_eq!(limiter.reserve(start, 512), Duration::ZERO);
+        assert_eq!(limiter.reserve(start, 512), Duration::from_millis(500));
+        assert_eq!(limiter.reserve(start, 1024), Duration::from_secs(1));
+        // Time spent idle is not banked for a later burst.
+        let later = start + Duration::from_secs(10);
+        assert_eq!(limiter.reserve(later, 1024), Duration::ZERO);
+        assert_eq!(limiter.reserve(later, 1), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn splits_large_uploads_into_parts() {
+        assert_eq!(plan_parts(10 * 1024 * 1024, 4), vec![0..10 * 1024 * 1024]);
+        assert_eq!(plan_parts(0, 4), vec![0..0]);
+
+        let total = 3 * PARALLEL_MIN_PART_SIZE + 7;
+        let parts = plan_parts(total, 8);
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[0].start, 0);
+        assert_eq!(parts[2].end, total);
+        assert!(parts.windows(2).all(|pair| pair[0].end == pair[1].start));
+
+        assert_eq!(plan_parts(total, 2).len(), 2);
+        assert_eq!(plan_parts(total, 0).len(), 1);
+    }
+
+    #[test]
+    fn parses_sha256sum_output() {
+        let hash = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        assert_eq!(
+            parse_sha256sum_output(&format!("{} ./file\n", hash.to_uppercase())).as_deref(),
+            Some(hash)
+        );
+        assert_eq!(
+            parse_sha256sum_output("sha256sum: file: No such file"),
+            None
+        );
+        assert_eq!(parse_sha256sum_output(""), None);
+    }
+}
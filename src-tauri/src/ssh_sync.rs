@@ -0,0 +1,553 @@
+use crate::ssh::{self, CommandOptions, SSH};
+use crate::ssh_sftp::TransferOptions;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::io::AsyncReadExt;
+
+// GNU find prints the listing itself; elsewhere (BSD, busybox) each file is stat'ed, with GNU
+// and busybox `stat -c` tried before BSD `stat -f`. Records are `size\tmtime\tpath\0`.
+const REMOTE_LISTING_SCRIPT: &str = r#"if find . -maxdepth 0 -printf '' >/dev/null 2>&1; then
+  find . -type f -printf '%s\t%T@\t%P\0'
+else
+  find . -type f -exec sh -c 'for f do s=$(stat -c "%s %Y" -- "$f" 2>/dev/null || stat -f "%z %m" -- "$f") && printf "%s\t%s\t%s\0" "${s% *}" "${s#* }" "${f#./}"; done' sh {} +
+fi"#;
+const REMOTE_LISTING_OPTIONS: CommandOptions = CommandOptions {
+    timeout: Some(Duration::from_secs(120)),
+    max_output_bytes: None,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncDirection {
+    Push,
+    Pull,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SyncOptions {
+    /// Glob patterns (`*`, `**`, `?`). A pattern without `/` matches any path component,
+    /// one with `/` matches from the sync root. Excluding a directory excludes its contents.
+    pub exclude: Vec<String>,
+    pub dry_run: bool,
+    /// When sizes match but modification times differ, compare SHA-256 before transferring.
+    pub compare_hash: bool,
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        Self {
+            exclude: Vec::new(),
+            dry_run: false,
+            compare_hash: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncReason {
+    Missing,
+    SizeChanged,
+    Modified,
+    ContentChanged,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncAction {
+    pub path: String,
+    pub reason: SyncReason,
+    pub size: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    pub direction: SyncDirection,
+    pub dry_run: bool,
+    pub actions: Vec<SyncAction>,
+    pub unchanged: usize,
+    pub excluded: usize,
+    /// Files whose names are not valid UTF-8. They are left alone on both sides.
+    pub skipped: usize,
+    pub bytes: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncProgress {
+    pub percent: u8,
+    pub files_done: usize,
+    pub files_total: usize,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub current_path: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct FileEntry {
+    size: u64,
+    mtime: i64,
+}
+
+#[derive(Debug, Default)]
+struct Listing {
+    files: BTreeMap<String, FileEntry>,
+    excluded: usize,
+    skipped: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Decision {
+    Transfer(SyncReason),
+    Unchanged,
+    CompareHash,
+}
+
+/// Transfers the files under `local_root`/`remote_root` that differ on the destination side.
+/// Files that only exist on the destination are left alone.
+pub async fn sync_directory(
+    ssh: &SSH,
+    local_root: &Path,
+    remote_root: &str,
+    direction: SyncDirection,
+    options: &SyncOptions,
+    mut on_progress: impl FnMut(&SyncProgress) -> Result<()>,
+) -> Result<SyncReport> {
+    let local = {
+        let local_root = local_root.to_path_buf();
+        let exclude = options.exclude.clone();
+        tauri::async_runtime::spawn_blocking(move || list_local(&local_root, &exclude))
+            .await
+            .map_err(|e| anyhow::anyhow!("Local listing failed: {e}"))??
+    };
+    let remote = list_remote(ssh, remote_root, &options.exclude).await?;
+    let skipped = local.skipped + remote.skipped;
+
+    let (source, destination, excluded) = match direction {
+        SyncDirection::Push => (&local.files, &remote.files, local.excluded),
+        SyncDirection::Pull => (&remote.files, &local.files, remote.excluded),
+    };
+
+    let mut actions = Vec::new();
+    let mut unchanged = 0;
+    for (path, entry) in source {
+        let reason = match compare(entry, destination.get(path), options.compare_hash) {
+            Decision::Transfer(reason) => Some(reason),
+            Decision::Unchanged => None,
+            Decision::CompareHash => {
+                let local_hash = sha256_file(&local_file(local_root, path)).await?;
+                let remote_hash = ssh.remote_sha256(&remote_file(remote_root, path)).await?;
+                (local_hash != remote_hash).then_some(SyncReason::ContentChanged)
+            }
+        };
+        match reason {
+            Some(reason) => actions.push(SyncAction {
+                path: path.clone(),
+                reason,
+                size: entry.size,
+            }),
+            None => unchanged += 1,
+        }
+    }
+
+    let report = SyncReport {
+        direction,
+        dry_run: options.dry_run,
+        bytes: actions.iter().map(|action| action.size).sum(),
+        actions,
+        unchanged,
+        excluded,
+        skipped,
+    };
+    if options.dry_run {
+        return Ok(report);
+    }
+
+    if direction == SyncDirection::Push {
+        create_remote_parents(ssh, remote_root, &report.actions).await?;
+    }
+
+    let mut progress = SyncProgress {
+        percent: 0,
+        files_done: 0,
+        files_total: report.actions.len(),
+        bytes_done: 0,
+        bytes_total: report.bytes,
+        current_path: None,
+    };
+    on_progress(&progress)?;
+
+    for action in &report.actions {
+        let local_path = local_file(local_root, &action.path);
+        let remote_path = remote_file(remote_root, &action.path);
+        let mtime = source[&action.path].mtime;
+        let bytes_before = progress.bytes_done;
+        progress.current_path = Some(action.path.clone());
+
+        let mut on_file_progress = |transferred: u64, _total: u64| {
+            progress.bytes_done = bytes_before + transferred.min(action.size);
+            let percent = percent_of(progress.bytes_done, progress.bytes_total);
+            if percent != progress.percent {
+                progress.percent = percent;
+                on_progress(&progress)?;
+            }
+            Ok(())
+        };
+
+        match direction {
+            SyncDirection::Push => {
+                let transfer_options = TransferOptions {
+                    mtime: u32::try_from(mtime).ok(),
+                    ..TransferOptions::default()
+                };
+                ssh.upload_local_file(
+                    &local_path,
+                    &remote_path,
+                    &transfer_options,
+                    &mut on_file_progress,
+                )
+                .await?;
+            }
+            SyncDirection::Pull => {
+                ssh.download_to_local_file(
+                    &remote_path,
+                    &local_path,
+                    &TransferOptions::default(),
+                    &mut on_file_progress,
+                )
+                .await?;
+                set_local_mtime(&local_path, mtime)?;
+            }
+        }
+
+        progress.files_done += 1;
+        progress.bytes_done = bytes_before + action.size;
+        progress.percent = percent_of(progress.bytes_done, progress.bytes_total);
+        on_progress(&progress)?;
+    }
+
+    progress.current_path = None;
+    progress.percent = 100;
+    on_progress(&progress)?;
+    Ok(report)
+}
+
+fn compare(source: &FileEntry, destination: Option<&FileEntry>, compare_hash: bool) -> Decision {
+    match destination {
+        None => Decision::Transfer(SyncReason::Missing),
+        Some(destination) if destination.size != source.size => {
+            Decision::Transfer(SyncReason::SizeChanged)
+        }
+        Some(destination) if destination.mtime == source.mtime => Decision::Unchanged,
+        Some(_) if compare_hash => Decision::CompareHash,
+        Some(_) => Decision::Transfer(SyncReason::Modified),
+    }
+}
+
+fn list_local(root: &Path, exclude: &[String]) -> Result<Listing> {
+    let mut listing = Listing::default();
+    if !root.exists() {
+        return Ok(listing);
+    }
+
+    let mut walker = walkdir::WalkDir::new(root).min_depth(1).into_iter();
+    while let Some(entry) = walker.next() {
+        let entry = entry?;
+        let Some(relative) = relative_path(root, entry.path()) else {
+            log::warn!("Not syncing {}: name is not UTF-8", entry.path().display());
+            listing.skipped += 1;
+            if entry.file_type().is_dir() {
+                walker.skip_current_dir();
+            }
+            continue;
+        };
+        if is_excluded(&relative, exclude) {
+            listing.excluded += 1;
+            if entry.file_type().is_dir() {
+                walker.skip_current_dir();
+            }
+            continue;
+        }
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let metadata = entry.metadata()?;
+        let mtime = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+        listing.files.insert(
+            relative,
+            FileEntry {
+                size: metadata.len(),
+                mtime,
+            },
+        );
+    }
+    Ok(listing)
+}
+
+async fn list_remote(ssh: &SSH, root: &str, exclude: &[String]) -> Result<Listing> {
+    let escaped_root = ssh::shell_escape_remote_path(root);
+    // NUL-separated so any file name survives; a missing root is an empty listing.
+    let command = format!(
+        "if [ -d {escaped_root} ]; then cd {escaped_root} || exit 1\n{REMOTE_LISTING_SCRIPT}\nfi"
+    );
+    let output = ssh
+        .run_command_with_options(command, REMOTE_LISTING_OPTIONS)
+        .await?;
+    if output.exit_code()? != 0 {
+        anyhow::bail!("Listing {root} failed: {}", output.stderr.trim());
+    }
+
+    let mut listing = Listing::default();
+    for record in parse_remote_listing(&output.stdout_bytes) {
+        match record {
+            Ok((path, _)) if is_excluded(&path, exclude) => listing.excluded += 1,
+            Ok((path, entry)) => {
+                listing.files.insert(path, entry);
+            }
+            Err(path) => {
+                log::warn!("Not syncing {root}/{path}: name is not UTF-8");
+                listing.skipped += 1;
+            }
+        }
+    }
+    Ok(listing)
+}
+
+/// Parses `size\tmtime\tpath\0` records. A path that is not UTF-8 comes back as `Err` with
+/// its lossy form, so the caller can report it instead of syncing a mangled name.
+fn parse_remote_listing(listing: &[u8]) -> Vec<Result<(String, FileEntry), String>> {
+    listing
+        .split(|b| *b == 0)
+        .filter_map(|record| {
+            let mut fields = record.splitn(3, |b| *b == b'\t');
+            let size = std::str::from_utf8(fields.next()?)
+                .ok()?
+                .trim()
+                .parse()
+                .ok()?;
+            let mtime = std::str::from_utf8(fields.next()?)
+                .ok()?
+                .parse::<f64>()
+                .ok()?
+                .floor() as i64;
+            let path = fields.next()?;
+            let Ok(path) = std::str::from_utf8(path) else {
+                return Some(Err(String::from_utf8_lossy(path).into_owned()));
+            };
+            // Never let a listing escape the sync root.
+            if path.is_empty() || path.starts_with('/') || path.split('/').any(|c| c == "..") {
+                return None;
+            }
+            Some(Ok((path.to_string(), FileEntry { size, mtime })))
+        })
+        .collect()
+}
+
+async fn create_remote_parents(ssh: &SSH, remote_root: &str, actions: &[SyncAction]) -> Result<()> {
+    let parents = actions
+        .iter()
+        .map(|action| match action.path.rsplit_once('/') {
+            Some((parent, _)) => remote_file(remote_root, parent),
+            None => remote_root.to_string(),
+        })
+        .collect::<BTreeSet<_>>();
+    if parents.is_empty() {
+        return Ok(());
+    }
+
+    let escaped = parents
+        .iter()
+        .map(|parent| ssh::shell_escape_remote_path(parent))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let (output, code) = ssh.run_command(format!("mkdir -p -- {escaped}")).await?;
+    if code != 0 {
+        anyhow::bail!("Creating remote directories failed: {}", output.trim());
+    }
+    Ok(())
+}
+
+fn is_excluded(path: &str, patterns: &[String]) -> bool {
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.trim_matches('/');
+        if pattern.is_empty() {
+            return false;
+        }
+        if !pattern.contains('/') {
+            return path
+                .split('/')
+                .any(|component| glob_matches(pattern.as_bytes(), component.as_bytes()));
+        }
+        // Anchored patterns match the path itself or any directory above it.
+        path.match_indices('/')
+            .map(|(index, _)| &path[..index])
+            .chain(std::iter::once(path))
+            .any(|prefix| glob_matches(pattern.as_bytes(), prefix.as_bytes()))
+    })
+}
+
+fn glob_matches(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern {
+        [] => text.is_empty(),
+        [b'*', b'*', rest @ ..] => {
+            // `**/` may also match zero directories.
+            rest.strip_prefix(b"/")
+                .is_some_and(|after_slash| glob_matches(after_slash, text))
+                || (0..=text.len()).any(|start| glob_matches(rest, &text[start..]))
+        }
+        [b'*', rest @ ..] => {
+            let segment_end = text.iter().position(|c| *c == b'/').unwrap_or(text.len());
+            (0..=segment_end).any(|start| glob_matches(rest, &text[start..]))
+        }
+        [b'?', rest @ ..] => {
+            matches!(text.first(), Some(c) if *c != b'/') && glob_matches(rest, &text[1..])
+        }
+        [literal, rest @ ..] => text.first() == Some(literal) && glob_matches(rest, &text[1..]),
+    }
+}
+
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let components = relative
+        .components()
+        .map(|component| component.as_os_str().to_str())
+        .collect::<Option<Vec<_>>>()?;
+    Some(components.join("/"))
+}
+
+fn local_file(root: &Path, relative: &str) -> PathBuf {
+    relative
+        .split('/')
+        .fold(root.to_path_buf(), |path, component| path.join(component))
+}
+
+fn remote_file(root: &str, relative: &str) -> String {
+    format!("{}/{relative}", root.trim_end_matches('/'))
+}
+
+fn percent_of(done: u64, total: u64) -> u8 {
+    match total {
+        0 => 100,
+        total => (done.saturating_mul(100) / total).min(100) as u8,
+    }
+}
+
+fn set_local_mtime(path: &Path, mtime: i64) -> Result<()> {
+    let Ok(seconds) = u64::try_from(mtime) else {
+        return Ok(());
+    };
+    std::fs::File::options()
+        .write(true)
+        .open(path)?
+        .set_modified(UNIX_EPOCH + Duration::from_secs(seconds))?;
+    Ok(())
+}
+
+async fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buffer).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn excludes_by_component_and_anchored_patterns() {
+        let exclude = patterns(&["*.log", "node_modules", "data/cache/", "build/**/*.tmp"]);
+
+        assert!(is_excluded("argon.log", &exclude));
+        assert!(is_excluded("logs/argon.log", &exclude));
+        assert!(is_excluded("app/node_modules/pkg/index.js", &exclude));
+        assert!(is_excluded("data/cache", &exclude));
+        assert!(is_excluded("data/cache/blocks/1.bin", &exclude));
+        assert!(is_excluded("build/out.tmp", &exclude));
+        assert!(is_excluded("build/a/b/out.tmp", &exclude));
+
+        assert!(!is_excluded("argon.log.json", &exclude));
+        assert!(!is_excluded("other/data/cache/1.bin", &exclude));
+        assert!(!is_excluded("build/out.bin", &exclude));
+        assert!(!is_excluded("config/settings.json", &exclude));
+    }
+
+    #[test]
+    fn compares_by_size_then_mtime() {
+        let source = FileEntry {
+            size: 10,
+            mtime: 100,
+        };
+
+        assert_eq!(
+            compare(&source, None, true),
+            Decision::Transfer(SyncReason::Missing)
+        );
+        assert_eq!(
+            compare(&source, Some(&FileEntry { size: 11, ..source }), true),
+            Decision::Transfer(SyncReason::SizeChanged)
+        );
+        assert_eq!(compare(&source, Some(&source), true), Decision::Unchanged);
+        assert_eq!(
+            compare(&source, Some(&FileEntry { mtime: 5, ..source }), true),
+            Decision::CompareHash
+        );
+        assert_eq!(
+            compare(&source, Some(&FileEntry { mtime: 5, ..source }), false),
+            Decision::Transfer(SyncReason::Modified)
+        );
+    }
+
+    #[test]
+    fn parses_remote_listing_and_rejects_escaping_paths() {
+        let mut listing = b"12\t1700000000.7500000000\tconfig/a b.json\0\
+                       0\t1700000001\tempty\0\
+                       5\t1700000002.0\t../outside\0\
+                       garbage\0"
+            .to_vec();
+        listing.extend_from_slice(b"3\t1700000003\tbad\xff\0");
+
+        assert_eq!(
+            parse_remote_listing(&listing),
+            vec![
+                Ok((
+                    "config/a b.json".to_string(),
+                    FileEntry {
+                        size: 12,
+                        mtime: 1_700_000_000
+                    }
+                )),
+                Ok((
+                    "empty".to_string(),
+                    FileEntry {
+                        size: 0,
+                        mtime: 1_700_000_001
+                    }
+                )),
+                Err("bad\u{fffd}".to_string()),
+            ]
+        );
+    }
+}
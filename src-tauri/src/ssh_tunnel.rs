@@ -0,0 +1,439 @@
+use crate::{ssh_operations, ssh_pool};
+use anyhow::Result;
+use lazy_static::lazy_static;
+use russh::Channel;
+use russh::client::Msg;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{Mutex, watch};
+
+lazy_static! {
+    static ref TUNNELS_BY_LOCAL_PORT: Mutex<HashMap<u16, Tunnel>> = Mutex::new(HashMap::new());
+    static ref REMOTE_TUNNELS: Mutex<HashMap<(String, u16), RemoteTunnel>> =
+        Mutex::new(HashMap::new());
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TunnelDirection {
+    /// Listens on the desktop and connects out from the server (`ssh -L`).
+    Local,
+    /// Listens on the server and connects out from the desktop (`ssh -R`).
+    Remote,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TunnelInfo {
+    pub direction: TunnelDirection,
+    pub address: String,
+    pub local_port: u16,
+    pub remote_host: String,
+    pub remote_port: u16,
+    pub opened_at: i64,
+    pub active_connections: usize,
+    pub total_connections: u64,
+}
+
+struct Tunnel {
+    address: String,
+    remote_host: String,
+    remote_port: u16,
+    opened_at: i64,
+    active_connections: Arc<AtomicUsize>,
+    total_connections: Arc<AtomicU64>,
+    shutdown: watch::Sender<bool>,
+}
+
+impl Tunnel {
+    fn info(&self, local_port: u16) -> TunnelInfo {
+        TunnelInfo {
+            direction: TunnelDirection::Local,
+            address: self.address.clone(),
+            local_port,
+            remote_host: self.remote_host.clone(),
+            remote_port: self.remote_port,
+            opened_at: self.opened_at,
+            active_connections: self.active_connections.load(Ordering::Relaxed),
+            total_connections: self.total_connections.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A server-side listener whose connections are forwarded to `127.0.0.1:local_port` here.
+struct RemoteTunnel {
+    /// `SSHConfig::known_host_key` of the connection, which forwarded channels arrive tagged with.
+    ssh_host: String,
+    bind_address: String,
+    local_port: u16,
+    opened_at: i64,
+    active_connections: Arc<AtomicUsize>,
+    total_connections: Arc<AtomicU64>,
+    shutdown: watch::Sender<bool>,
+}
+
+impl RemoteTunnel {
+    fn info(&self, address: &str, remote_port: u16) -> TunnelInfo {
+        TunnelInfo {
+            direction: TunnelDirection::Remote,
+            address: address.to_string(),
+            local_port: self.local_port,
+            remote_host: self.bind_address.clone(),
+            remote_port,
+            opened_at: self.opened_at,
+            active_connections: self.active_connections.load(Ordering::Relaxed),
+            total_connections: self.total_connections.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Listens on `127.0.0.1:local_port` and forwards each accepted connection to
+/// `remote_host:remote_port` as seen from the server, over the pooled connection for `address`.
+/// A `local_port` of 0 picks a free port.
+pub async fn open_tunnel(
+    address: &str,
+    local_port: u16,
+    remote_host: &str,
+    remote_port: u16,
+) -> Result<TunnelInfo> {
+    if ssh_pool::get_connection(address).await?.is_none() {
+        anyhow::bail!("No SSH connection");
+    }
+
+    let mut tunnels = TUNNELS_BY_LOCAL_PORT.lock().await;
+    if tunnels.contains_key(&local_port) {
+        anyhow::bail!("A tunnel is already open on local port {local_port}");
+    }
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, local_port)).await?;
+    let local_port = listener.local_addr()?.port();
+
+    let (shutdown, shutdown_receiver) = watch::channel(false);
+    let tunnel = Tunnel {
+        address: address.to_string(),
+        remote_host: remote_host.to_string(),
+        remote_port,
+        opened_at: now_seconds(),
+        active_connections: Arc::new(AtomicUsize::new(0)),
+        total_connections: Arc::new(AtomicU64::new(0)),
+        shutdown,
+    };
+    let info = tunnel.info(local_port);
+
+    log::info!(
+        "Opening SSH tunnel 127.0.0.1:{local_port} -> {remote_host}:{remote_port} via {address}"
+    );
+    tauri::async_runtime::spawn(accept_connections(
+        listener,
+        address.to_string(),
+        remote_host.to_string(),
+        remote_port,
+        tunnel.active_connections.clone(),
+        tunnel.total_connections.clone(),
+        shutdown_receiver,
+    ));
+    tunnels.insert(local_port, tunnel);
+    Ok(info)
+}
+
+/// Has the server listen on `bind_address:remote_port` and forwards each connection it accepts
+/// to `127.0.0.1:local_port` on this machine. A `remote_port` of 0 lets the server pick one.
+pub async fn open_remote_tunnel(
+    address: &str,
+    bind_address: &str,
+    remote_port: u16,
+    local_port: u16,
+) -> Result<TunnelInfo> {
+    let ssh = ssh_pool::get_connection(address)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("No SSH connection"))?;
+    if REMOTE_TUNNELS
+        .lock()
+        .await
+        .contains_key(&(address.to_string(), remote_port))
+    {
+        anyhow::bail!("A remote tunnel is already open on port {remote_port}");
+    }
+    // Not under the registry lock: a reconnect reads the registry while holding the client.
+    let remote_port = ssh
+        .request_remote_forward(bind_address, remote_port)
+        .await?;
+
+    let (shutdown, _) = watch::channel(false);
+    let tunnel = RemoteTunnel {
+        ssh_host: ssh.config.known_host_key(),
+        bind_address: bind_address.to_string(),
+        local_port,
+        opened_at: now_seconds(),
+        active_connections: Arc::new(AtomicUsize::new(0)),
+        total_connections: Arc::new(AtomicU64::new(0)),
+        shutdown,
+    };
+    let info = tunnel.info(address, remote_port);
+    log::info!(
+        "Opening remote SSH tunnel {bind_address}:{remote_port} -> 127.0.0.1:{local_port} via {address}"
+    );
+    REMOTE_TUNNELS
+        .lock()
+        .await
+        .insert((address.to_string(), remote_port), tunnel);
+    Ok(info)
+}
+
+/// Stops the server listener and drops every connection forwarded through it.
+pub async fn close_remote_tunnel(address: &str, remote_port: u16) -> Result<bool> {
+    let Some(tunnel) = REMOTE_TUNNELS
+        .lock()
+        .await
+        .remove(&(address.to_string(), remote_port))
+    else {
+        return Ok(false);
+    };
+    log::info!("Closing remote SSH tunnel on port {remote_port} via {address}");
+    let _ = tunnel.shutdown.send(true);
+    if let Some(ssh) = ssh_pool::get_connection(address).await? {
+        ssh.cancel_remote_forward(&tunnel.bind_address, remote_port)
+            .await?;
+    }
+    Ok(true)
+}
+
+/// The remote forwards registered on a connection, so a reconnect can request them again.
+pub async fn remote_forwards_for_host(ssh_host: &str) -> Vec<(String, u16)> {
+    REMOTE_TUNNELS
+        .lock()
+        .await
+        .iter()
+        .filter(|(_, tunnel)| tunnel.ssh_host == ssh_host)
+        .map(|((_, remote_port), tunnel)| (tunnel.bind_address.clone(), *remote_port))
+        .collect()
+}
+
+/// Handles a channel the server opened for a connection to one of our remote forwards.
+pub fn accept_forwarded(ssh_host: String, connected_port: u32, channel: Channel<Msg>) {
+    tauri::async_runtime::spawn(async move {
+        let tunnel = {
+            let tunnels = REMOTE_TUNNELS.lock().await;
+            tunnels
+                .iter()
+                .find(|((_, remote_port), tunnel)| {
+                    tunnel.ssh_host == ssh_host && u32::from(*remote_port) == connected_port
+                })
+                .map(|(_, tunnel)| {
+                    (
+                        tunnel.local_port,
+                        tunnel.active_connections.clone(),
+                        tunnel.total_connections.clone(),
+                        tunnel.shutdown.subscribe(),
+                    )
+                })
+        };
+        let Some((local_port, active_connections, total_connections, mut shutdown)) = tunnel else {
+            log::warn!(
+                "Rejecting forwarded connection to unknown port {connected_port} from {ssh_host}"
+            );
+            let _ = channel.close().await;
+            return;
+        };
+
+        total_connections.fetch_add(1, Ordering::Relaxed);
+        active_connections.fetch_add(1, Ordering::Relaxed);
+        let forwarded = async {
+            let mut socket = TcpStream::connect((Ipv4Addr::LOCALHOST, local_port)).await?;
+            let mut stream = channel.into_stream();
+            tokio::select! {
+                _ = shutdown.changed() => {}
+                copied = tokio::io::copy_bidirectional(&mut socket, &mut stream) => {
+                    copied?;
+                }
+            }
+            anyhow::Ok(())
+        };
+        if let Err(e) = forwarded.await {
+            log::warn!("Remote SSH tunnel to 127.0.0.1:{local_port} via {ssh_host} failed: {e:#}");
+        }
+        active_connections.fetch_sub(1, Ordering::Relaxed);
+    });
+}
+
+/// Stops listening and drops every connection forwarded through the tunnel.
+pub async fn close_tunnel(local_port: u16) -> bool {
+    match TUNNELS_BY_LOCAL_PORT.lock().await.remove(&local_port) {
+        Some(tunnel) => {
+            log::info!("Closing SSH tunnel on local port {local_port}");
+            let _ = tunnel.shutdown.send(true);
+            true
+        }
+        None => false,
+    }
+}
+
+pub async fn close_tunnels_for_address(address: &str) {
+    TUNNELS_BY_LOCAL_PORT
+        .lock()
+        .await
+        .retain(|local_port, tunnel| {
+            if tunnel.address != address {
+                return true;
+            }
+            log::info!("Closing SSH tunnel on local port {local_port} with its connection");
+            let _ = tunnel.shutdown.send(true);
+            false
+        });
+    // The server drops its listeners with the connection.
+    REMOTE_TUNNELS
+        .lock()
+        .await
+        .retain(|(tunnel_address, remote_port), tunnel| {
+            if tunnel_address != address {
+                return true;
+            }
+            log::info!("Closing remote SSH tunnel on port {remote_port} with its connection");
+            let _ = tunnel.shutdown.send(true);
+            false
+        });
+}
+
+pub async fn list_tunnels() -> Vec<TunnelInfo> {
+    let mut tunnels = TUNNELS_BY_LOCAL_PORT
+        .lock()
+        .await
+        .iter()
+        .map(|(local_port, tunnel)| tunnel.info(*local_port))
+        .collect::<Vec<_>>();
+    tunnels.extend(
+        REMOTE_TUNNELS
+            .lock()
+            .await
+            .iter()
+            .map(|((address, remote_port), tunnel)| tunnel.info(address, *remote_port)),
+    );
+    tunnels.sort_by_key(|tunnel| {
+        (
+            tunnel.direction == TunnelDirection::Remote,
+            tunnel.local_port,
+        )
+    });
+    tunnels
+}
+
+fn now_seconds() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+async fn accept_connections(
+    listener: TcpListener,
+    address: String,
+    remote_host: String,
+    remote_port: u16,
+    active_connections: Arc<AtomicUsize>,
+    total_connections: Arc<AtomicU64>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    loop {
+        let (socket, peer) = tokio::select! {
+            _ = shutdown.changed() => return,
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    log::warn!("SSH tunnel failed to accept a connection: {e}");
+                    continue;
+                }
+            },
+        };
+
+        total_connections.fetch_add(1, Ordering::Relaxed);
+        let address = address.clone();
+        let remote_host = remote_host.clone();
+        let active_connections = active_connections.clone();
+        let shutdown = shutdown.clone();
+        tauri::async_runtime::spawn(async move {
+            active_connections.fetch_add(1, Ordering::Relaxed);
+            if let Err(e) =
+                forward_connection(socket, peer, &address, &remote_host, remote_port, shutdown)
+                    .await
+            {
+                log::warn!("SSH tunnel to {remote_host}:{remote_port} via {address} failed: {e:#}");
+            }
+            active_connections.fetch_sub(1, Ordering::Relaxed);
+        });
+    }
+}
+
+async fn forward_connection(
+    mut socket: TcpStream,
+    peer: SocketAddr,
+    address: &str,
+    remote_host: &str,
+    remote_port: u16,
+    mut shutdown: watch::Receiver<bool>,
+) -> Result<()> {
+    // Look the connection up per socket so tunnels follow a connection that was recreated.
+    let ssh = ssh_pool::get_connection(address)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("No SSH connection"))?;
+    let channel = ssh
+        .open_direct_tcpip(remote_host, remote_port, peer)
+        .await?;
+    let mut stream = channel.into_stream();
+
+    tokio::select! {
+        _ = shutdown.changed() => {}
+        copied = tokio::io::copy_bidirectional(&mut socket, &mut stream) => {
+            copied?;
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn ssh_open_tunnel(
+    address: &str,
+    local_port: u16,
+    remote_host: &str,
+    remote_port: u16,
+) -> Result<TunnelInfo, String> {
+    ssh_operations::ensure_unrestricted_action_allowed("opening tunnels")?;
+    open_tunnel(address, local_port, remote_host, remote_port)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn ssh_open_remote_tunnel(
+    address: &str,
+    bind_address: Option<String>,
+    remote_port: u16,
+    local_port: u16,
+) -> Result<TunnelInfo, String> {
+    ssh_operations::ensure_unrestricted_action_allowed("opening tunnels")?;
+    // Loopback by default, like `ssh -R`, so the port is not exposed beyond the server.
+    let bind_address = bind_address.unwrap_or_else(|| "127.0.0.1".to_string());
+    open_remote_tunnel(address, &bind_address, remote_port, local_port)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn ssh_close_remote_tunnel(address: &str, remote_port: u16) -> Result<bool, String> {
+    close_remote_tunnel(address, remote_port)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn ssh_close_tunnel(local_port: u16) -> Result<bool, String> {
+    Ok(close_tunnel(local_port).await)
+}
+
+#[tauri::command]
+pub async fn ssh_list_tunnels() -> Result<Vec<TunnelInfo>, String> {
+    Ok(list_tunnels().await)
+}
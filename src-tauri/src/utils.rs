@@ -1,5 +1,6 @@
 use anyhow::Result;
 use rand::RngCore;
+use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
 use std::collections::HashMap;
 use std::io::Cursor;
 use std::path::{Path, PathBuf};
@@ -21,6 +22,11 @@ static DEV_DOCKER_SERVER_ENV_PASSTHROUGH: &[&str] = &[
     "NOTARY_ALIAS_CONTAINER_ID",
 ];
 
+/// Generic Substrate prefix, used off mainnet unless the network config names one.
+const DEFAULT_SS58_PREFIX: u16 = 42;
+/// Argon's registered prefix, so mainnet addresses are told apart from testnet ones.
+const MAINNET_SS58_PREFIX: u16 = 18;
+
 pub struct Utils;
 
 impl Utils {
@@ -77,6 +83,29 @@ impl Utils {
         .into()
     }
 
+    /// `ARGON_SS58_PREFIX` from the network's env config, else the network's default prefix.
+    pub fn get_ss58_prefix(app_id: &str) -> u16 {
+        let env_vars = Self::get_server_env_vars(app_id).unwrap_or_default();
+        Self::ss58_prefix_for(&Self::get_network_name(app_id), &env_vars)
+    }
+
+    fn ss58_prefix_for(network_name: &str, env_vars: &HashMap<String, String>) -> u16 {
+        env_vars
+            .get("ARGON_SS58_PREFIX")
+            .and_then(|prefix| prefix.parse().ok())
+            .unwrap_or(match network_name {
+                "mainnet" => MAINNET_SS58_PREFIX,
+                _ => DEFAULT_SS58_PREFIX,
+            })
+    }
+
+    /// Re-encodes an SS58 address (in any prefix) with the given network prefix.
+    pub fn convert_ss58_address(address: &str, prefix: u16) -> Result<String> {
+        let (account, _format) = AccountId32::from_ss58check_with_version(address.trim())
+            .map_err(|e| anyhow::anyhow!("Invalid SS58 address {address}: {e:?}"))?;
+        Ok(account.to_ss58check_with_version(Ss58AddressFormat::custom(prefix)))
+    }
+
     pub fn get_relative_config_instance_dir(app_id: &str) -> PathBuf {
         let instance_name = Self::get_instance_name();
         let network_name = Self::get_network_name(app_id);
@@ -131,3 +160,33 @@ impl Utils {
         Ok(key)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_the_ss58_prefix_from_the_network() {
+        let no_env = HashMap::new();
+        assert_eq!(Utils::ss58_prefix_for("mainnet", &no_env), 18);
+        assert_eq!(Utils::ss58_prefix_for("testnet", &no_env), 42);
+        assert_eq!(Utils::ss58_prefix_for("localnet", &no_env), 42);
+
+        let configured = HashMap::from([("ARGON_SS58_PREFIX".to_string(), "7".to_string())]);
+        assert_eq!(Utils::ss58_prefix_for("mainnet", &configured), 7);
+    }
+
+    #[test]
+    fn converts_wallet_addresses_between_ss58_prefixes() {
+        let generic = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+        let converted = Utils::convert_ss58_address(generic, 18).expect("address should convert");
+        assert_ne!(converted, generic);
+        let (_account, format) = AccountId32::from_ss58check_with_version(&converted)
+            .expect("converted address should decode");
+        assert_eq!(u16::from(format), 18);
+        assert_eq!(
+            Utils::convert_ss58_address(&converted, 42).expect("address should convert back"),
+            generic
+        );
+    }
+}
@@ -3,12 +3,13 @@ import { ServerType } from '../interfaces/IConfig.ts';
 import { SSHConnection } from '../lib/SSHConnection.ts';
 import { InvokeTimeout } from '../lib/tauriApi.ts';
 
-const { invokeWithTimeout, listen, MockInvokeTimeout } = vi.hoisted(() => {
+const { invokeWithTimeout, listen, confirm, MockInvokeTimeout } = vi.hoisted(() => {
   class MockInvokeTimeout extends Error {}
 
   return {
     invokeWithTimeout: vi.fn(),
     listen: vi.fn(),
+    confirm: vi.fn(),
     MockInvokeTimeout,
   };
 });
@@ -26,9 +27,16 @@ vi.mock('@tauri-apps/api/event', () => {
   };
 });
 
+vi.mock('@tauri-apps/plugin-dialog', () => {
+  return {
+    confirm,
+  };
+});
+
 describe('SSHConnection', () => {
   beforeEach(() => {
     invokeWithTimeout.mockReset();
+    confirm.mockReset();
     listen.mockReset();
     listen.mockResolvedValue(vi.fn());
   });
@@ -62,6 +70,68 @@ describe('SSHConnection', () => {
     ]);
   });
 
+  it('re-pins a changed host key once the user trusts it', async () => {
+    invokeWithTimeout
+      .mockRejectedValueOnce('SSHHostKeyChanged: 127.0.0.1:55404 presented SHA256:new but SHA256:old is pinned')
+      .mockResolvedValueOnce({
+        host: '127.0.0.1:55404',
+        pinned: { fingerprint: 'SHA256:old' },
+        presentedFingerprint: 'SHA256:new',
+        matchesPinned: false,
+      })
+      .mockResolvedValueOnce({})
+      .mockResolvedValueOnce('success')
+      .mockResolvedValueOnce('success');
+    confirm.mockResolvedValueOnce(true);
+
+    const connection = new SSHConnection({
+      type: ServerType.LocalComputer,
+      ipAddress: '127.0.0.1',
+      sshPort: 55404,
+      sshUser: 'root',
+      workDir: '/app',
+    });
+
+    await connection.connect(0);
+
+    expect(connection.isConnected).toBe(true);
+    expect(invokeWithTimeout.mock.calls.map(call => call[0] as string)).toEqual([
+      'open_ssh_connection',
+      'ssh_inspect_known_host',
+      'ssh_repin_known_host',
+      'close_ssh_connection',
+      'open_ssh_connection',
+    ]);
+    expect(invokeWithTimeout.mock.calls[2][1]).toMatchObject({ expectedFingerprint: 'SHA256:new' });
+  });
+
+  it('does not connect when the user rejects a changed host key', async () => {
+    vi.spyOn(console, 'error').mockImplementation(() => undefined);
+    invokeWithTimeout
+      .mockRejectedValueOnce('SSHHostKeyChanged: 127.0.0.1:55404 presented SHA256:new but SHA256:old is pinned')
+      .mockResolvedValueOnce({
+        host: '127.0.0.1:55404',
+        pinned: { fingerprint: 'SHA256:old' },
+        presentedFingerprint: 'SHA256:new',
+        matchesPinned: false,
+      });
+    confirm.mockResolvedValueOnce(false);
+
+    const connection = new SSHConnection({
+      type: ServerType.LocalComputer,
+      ipAddress: '127.0.0.1',
+      sshPort: 55404,
+      sshUser: 'root',
+      workDir: '/app',
+    });
+
+    await expect(connection.connect(0)).rejects.toMatch('SSHHostKeyChanged');
+    expect(invokeWithTimeout.mock.calls.map(call => call[0] as string)).toEqual([
+      'open_ssh_connection',
+      'ssh_inspect_known_host',
+    ]);
+  });
+
   it('reconnects when the SSH pool no longer has the connection', async () => {
     invokeWithTimeout
       .mockResolvedValueOnce('success')
@@ -124,14 +194,36 @@ describe('SSHConnection', () => {
     warning.mockRestore();
   });
 
+  it('does not start a second embedded upload when the invoke itself times out', async () => {
+    invokeWithTimeout.mockRejectedValueOnce(new InvokeTimeout('Invoke timed out'));
+
+    const connection = new SSHConnection({
+      type: ServerType.LocalComputer,
+      ipAddress: '127.0.0.1',
+      sshPort: 55404,
+      sshUser: 'root',
+      workDir: '/app',
+    });
+
+    await expect(
+      connection.uploadEmbeddedFileWithTimeout(
+        'resources/server-2.3.5.tar.gz',
+        '/app/server-2.3.5.tar.gz',
+        vi.fn(),
+        120_000,
+      ),
+    ).rejects.toThrow('streaming, last progress 0%');
+    expect(invokeWithTimeout.mock.calls.map(call => call[0] as string)).toEqual(['ssh_upload_embedded_file']);
+  });
+
   it('reports upload progress when the final transfer attempt times out', async () => {
-    let emitProgress: ((event: { payload: number }) => void) | undefined;
+    let emitProgress: ((event: { payload: { percent: number } }) => void) | undefined;
     listen.mockImplementation(async (_eventName, callback) => {
       emitProgress = callback;
       return vi.fn();
     });
     invokeWithTimeout.mockImplementationOnce(async () => {
-      emitProgress?.({ payload: 37 });
+      emitProgress?.({ payload: { percent: 37 } });
       throw new Error('SSH upload timed out after 120s');
     });
 
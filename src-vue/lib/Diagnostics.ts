@@ -43,14 +43,14 @@ export class Diagnostics {
   public async remoteServerFilesAreUpToDate() {
     // TODO: Will finish sha256 check when Blake pushes his latest changes
     const workdir = this.config.serverDetails.workDir;
-    const [outputRaw] = await SSH.runCommand(`ls -la ${workdir}/server`);
+    const [outputRaw] = await SSH.runCommand(`ls -la ${workdir}/server`, 3, 'diagnostics');
     const files = this.extractCleanFiles(outputRaw);
     return { files };
   }
 
   public async remoteConfigFilesAreUpToDate() {
     const workdir = this.config.serverDetails.workDir;
-    const [outputRaw] = await SSH.runCommand(`ls -la ${workdir}/config`);
+    const [outputRaw] = await SSH.runCommand(`ls -la ${workdir}/config`, 3, 'diagnostics');
     const files = this.extractCleanFiles(outputRaw);
     return { files };
   }
@@ -759,7 +759,7 @@ export default class Installer {
     const totalCount = 120;
     try {
       console.log(`Removing ${remoteDir}`);
-      await SSH.runCommand(`rm -rf ${remoteDir} && mkdir -p ${remoteDir}`);
+      await SSH.runCommand(`rm -rf ${remoteDir} && mkdir -p ${remoteDir}`, 3, 'installer');
       totalProgress += 10;
       progressFn?.(totalCount, totalProgress);
     } catch (e) {
@@ -776,7 +776,7 @@ export default class Installer {
         progressFn?.(totalCount, totalProgress);
       });
 
-      const [remoteSha256] = await SSH.runCommand(`cd ${workDir} && sha256sum ${serverTar}`);
+      const [remoteSha256] = await SSH.runCommand(`cd ${workDir} && sha256sum ${serverTar}`, 3, 'installer');
       if (remoteSha256.replace(/\s+/, ' ').trim() !== expectedSha.replace(/\s+/, ' ').trim()) {
         console.log(`Remote SHA256: ${remoteSha256}`);
         console.log(`Embedded SHA256: ${expectedSha}`);
@@ -791,7 +791,7 @@ export default class Installer {
 
     try {
       console.log(`Extracting server files to ${remoteDir}`);
-      const [result, status] = await SSH.runCommand(`tar -xzf ${workDir}/${serverTar} -C ${remoteDir}`);
+      const [result, status] = await SSH.runCommand(`tar -xzf ${workDir}/${serverTar} -C ${remoteDir}`, 3, 'installer');
       if (status !== 0) {
         throw new Error(`Failed to extract server files: ${result}`);
       }
@@ -76,10 +76,10 @@ export class SSH {
     return error instanceof InvokeTimeout || String(error) === 'No SSH connection';
   }
 
-  public static async runCommand(command: string, retries = 3): Promise<[string, number]> {
+  public static async runCommand(command: string, retries = 3, origin?: string): Promise<[string, number]> {
     const connection = await this.getOrCreateConnection();
     try {
-      return await connection.runCommandWithTimeout(command, 60 * 1e3);
+      return await connection.runCommandWithTimeout(command, 60 * 1e3, 1, origin);
     } catch (e) {
       const hasRetries = retries > 0;
       let shouldRetry = e === 'SSHCommandMissingExitStatus' && hasRetries;
@@ -90,7 +90,7 @@ export class SSH {
 
       if (shouldRetry) {
         console.error(`SSH command timed out, retrying (${3 - retries + 1}/${3})...`, command);
-        return this.runCommand(command, retries - 1);
+        return this.runCommand(command, retries - 1, origin);
       }
       console.error(`Error running command ${command}`, e);
 
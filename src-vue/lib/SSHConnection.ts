@@ -1,9 +1,32 @@
 import { InvokeTimeout, invokeWithTimeout } from './tauriApi';
 import { listen } from '@tauri-apps/api/event';
+import { confirm } from '@tauri-apps/plugin-dialog';
 import { IConfigServerDetails, ServerType } from '../interfaces/IConfig.ts';
 
 export type ISSHConfig = IConfigServerDetails;
 
+// The backend aborts an embedded upload once it stalls for `timeout`; this only catches a hung invoke. Hitting it
+// must not retry, because the first upload may still be writing the same partial files.
+const EMBEDDED_UPLOAD_CEILING_MS = 6 * 60 * 60e3;
+
+export interface ISSHTransferProgress {
+  percent: number;
+  rawBytes: number;
+  wireBytes: number;
+  totalBytes: number;
+  bytesPerSec: number;
+  compression: 'zstd' | 'gzip' | null;
+}
+
+export interface ISSHKnownHostInspection {
+  host: string;
+  pinned: { fingerprint: string } | null;
+  presentedFingerprint: string;
+  matchesPinned: boolean;
+}
+
+export type ISSHTransferProgressCallback = (progress: number, stats: ISSHTransferProgress) => void;
+
 export class SSHConnection {
   public isConnected = false;
   public isConnectedPromise?: Promise<void>;
@@ -49,6 +72,10 @@ export class SSHConnection {
         resolve();
       } catch (error) {
         const errorString = String(error).toLowerCase();
+        if (errorString.includes('sshhostkeychanged') && !this.isDestroyed && (await this.confirmHostKeyChange())) {
+          await this.close().catch(() => undefined);
+          return this.connect(retries).then(resolve, reject);
+        }
         const shouldRetry =
           retries > 0 &&
           !this.isDestroyed &&
@@ -77,8 +104,54 @@ export class SSHConnection {
     return this.isConnectedPromise;
   }
 
-  public async runCommandWithTimeout(command: string, timeout: number, retries = 1): Promise<[string, number]> {
-    const payload = { address: this.address, command };
+  /**
+   * Shows the pinned and presented fingerprints and re-pins the presented key if the user trusts it,
+   * e.g. after the server was rebuilt or its IP reused.
+   */
+  private async confirmHostKeyChange(): Promise<boolean> {
+    try {
+      const hostArgs = { host: this.host, port: this.port };
+      const inspection = await invokeWithTimeout<ISSHKnownHostInspection>('ssh_inspect_known_host', hostArgs, 15e3);
+      if (inspection.matchesPinned) {
+        return true;
+      }
+      const trusted = await confirm(
+        `The server at ${this.address} presented a different identity than the one this app trusts.\n\n` +
+          `Trusted: ${inspection.pinned?.fingerprint ?? 'none'}\n` +
+          `Presented: ${inspection.presentedFingerprint}\n\n` +
+          'Only continue if you rebuilt or replaced this server. Otherwise someone may be intercepting the connection.',
+        {
+          title: 'Server Identity Changed',
+          kind: 'warning',
+          okLabel: 'Trust New Key',
+          cancelLabel: 'Cancel',
+        },
+      );
+      if (!trusted) {
+        return false;
+      }
+      await invokeWithTimeout(
+        'ssh_repin_known_host',
+        { ...hostArgs, expectedFingerprint: inspection.presentedFingerprint },
+        15e3,
+      );
+      return true;
+    } catch (error) {
+      console.error(`[SSHConnection] Could not re-pin the host key for ${this.address}`, error);
+      return false;
+    }
+  }
+
+  /**
+   * `origin` names the UI action running the command in the SSH audit log.
+   */
+  public async runCommandWithTimeout(
+    command: string,
+    timeout: number,
+    retries = 1,
+    origin?: string,
+  ): Promise<[string, number]> {
+    const payload = { address: this.address, command, origin };
 
     try {
       return await invokeWithTimeout('ssh_run_command', payload, timeout);
@@ -98,7 +171,7 @@ export class SSHConnection {
 
       await this.close().catch(() => undefined);
       await this.connect();
-      return await this.runCommandWithTimeout(command, timeout, retries - 1);
+      return await this.runCommandWithTimeout(command, timeout, retries - 1, origin);
     }
   }
 
@@ -107,18 +180,21 @@ export class SSHConnection {
     return await invokeWithTimeout('ssh_upload_file', payload, timeout);
   }
 
+  /**
+   * `timeout` is how long the transfer may go without progress, not a limit on the whole upload.
+   */
   public async uploadEmbeddedFileWithTimeout(
     localRelativePath: string,
     remotePath: string,
-    progressCallback: (progress: number) => void,
+    progressCallback: ISSHTransferProgressCallback,
     timeout: number,
     retries = 1,
   ): Promise<void> {
     const eventProgressKey = localRelativePath.replace(/[^a-zA-Z0-9]/g, '_') + '_up_progress';
     let lastProgress = 0;
-    const unsub = await listen(eventProgressKey, event => {
-      lastProgress = event.payload as number;
-      progressCallback(lastProgress);
+    const unsub = await listen<ISSHTransferProgress>(eventProgressKey, event => {
+      lastProgress = event.payload.percent;
+      progressCallback(lastProgress, event.payload);
       if (lastProgress === 100) {
         unsub(); // Unsubscribe when upload is complete
       }
@@ -131,12 +207,13 @@ export class SSHConnection {
         eventProgressKey,
         timeoutMs: timeout,
       };
-      await invokeWithTimeout('ssh_upload_embedded_file', payload, timeout + 5_000);
+      await invokeWithTimeout('ssh_upload_embedded_file', payload, EMBEDDED_UPLOAD_CEILING_MS);
     } catch (error) {
       unsub();
       const errorMessage = String(error).toLowerCase();
-      const transferTimedOut = error instanceof InvokeTimeout || errorMessage.includes('ssh upload timed out');
-      if (retries > 0 && !this.isDestroyed && transferTimedOut) {
+      const transferStalled = errorMessage.includes('ssh upload timed out');
+      const transferTimedOut = error instanceof InvokeTimeout || transferStalled;
+      if (retries > 0 && !this.isDestroyed && transferStalled) {
         console.warn(
           `[SSHConnection] Embedded upload to ${remotePath} timed out at ${lastProgress}%; ` +
             'retrying with a fresh transfer connection',
@@ -160,13 +237,13 @@ export class SSHConnection {
   public async downloadFileWithTimeout(
     remotePath: string,
     downloadPath: string,
-    progressCallback: (progress: number) => void,
+    progressCallback: ISSHTransferProgressCallback,
     timeout: number,
   ): Promise<void> {
     const eventProgressKey = remotePath.replace(/[^a-zA-Z0-9]/g, '_') + '_dl_progress';
-    const unsub = await listen(eventProgressKey, event => {
-      progressCallback(event.payload as number);
-      if (event.payload === 100) {
+    const unsub = await listen<ISSHTransferProgress>(eventProgressKey, event => {
+      progressCallback(event.payload.percent, event.payload);
+      if (event.payload.percent === 100) {
         unsub(); // Unsubscribe when upload is complete
       }
     });